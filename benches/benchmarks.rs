@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use purple::bench::{eval, parse_fen, BoardState, MoveGenerator, ZobristTable};
+
+const STARTING: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const ENDGAME: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const MIDDLEGAME: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn positions() -> Vec<(&'static str, BoardState)> {
+    vec![
+        ("starting", parse_fen(STARTING).unwrap()),
+        ("kiwipete", parse_fen(KIWIPETE).unwrap()),
+        ("endgame", parse_fen(ENDGAME).unwrap()),
+        ("middlegame", parse_fen(MIDDLEGAME).unwrap()),
+    ]
+}
+
+fn bench_all_moves(c: &mut Criterion) {
+    let gen = MoveGenerator::new();
+    let mut group = c.benchmark_group("all_moves");
+    for (name, pos) in positions() {
+        group.bench_function(name, |b| b.iter(|| gen.all_moves(black_box(&pos))));
+    }
+    group.finish();
+}
+
+fn bench_eval(c: &mut Criterion) {
+    let pos = parse_fen(MIDDLEGAME).unwrap();
+    c.bench_function("eval middlegame", |b| b.iter(|| eval(black_box(&pos))));
+}
+
+fn bench_zobrist_hash(c: &mut Criterion) {
+    let zobrist = ZobristTable::init();
+    let mut pos = parse_fen(KIWIPETE).unwrap();
+    c.bench_function("zobrist hash kiwipete", |b| {
+        b.iter(|| zobrist.hash(black_box(&mut pos)))
+    });
+}
+
+criterion_group!(benches, bench_all_moves, bench_eval, bench_zobrist_hash);
+criterion_main!(benches);
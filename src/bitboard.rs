@@ -1,5 +1,6 @@
 use crate::chess_move::{EAST, NORTH, SOUTH, WEST};
-use crate::square::{rank_file_to_index, Square};
+use crate::piece::{Color, COLOR_COUNT};
+use crate::square::{rank_file_to_index, square_to_file, square_to_rank, Square};
 
 pub type Bitboard = u64;
 
@@ -22,6 +23,60 @@ pub const FILEF: Bitboard = FILEA << 5;
 pub const FILEG: Bitboard = FILEA << 6;
 pub const FILEH: Bitboard = FILEA << 7;
 
+/// The dark squares of the board, including a1 and h8.
+pub const DARK_SQUARES: Bitboard = 0xAA55_AA55_AA55_AA55;
+
+/// Returns a bitboard of the entire file which contains the given square.
+pub fn file_mask(square: Square) -> Bitboard {
+    FILEA << square_to_file(square)
+}
+
+/// Returns a bitboard of the entire rank which contains the given square.
+pub fn rank_mask(square: Square) -> Bitboard {
+    RANK1 << (8 * square_to_rank(square))
+}
+
+/// For each color and square, the squares which must be free of opposing pawns for a pawn of that
+/// color on that square to be passed: every square in front of it on its own and adjacent files.
+pub const PASSED_PAWN_MASKS: [[Bitboard; 64]; COLOR_COUNT] = passed_pawn_masks();
+
+/// Returns a bitboard of the files either side of the file which contains the given square.
+#[must_use]
+pub const fn adjacent_files(square: Square) -> Bitboard {
+    let file = FILEA << (square % 8);
+    ((file << 1) & !FILEA) | ((file >> 1) & !FILEH)
+}
+
+/// Returns a bitboard of the squares in front of the given square on its file, as seen by a pawn
+/// of the given color.
+#[must_use]
+pub const fn front_span(square: Square, color: Color) -> Bitboard {
+    (FILEA << (square % 8)) & ranks_ahead(square, color)
+}
+
+/// Returns a bitboard of every rank in front of the rank which contains the given square, as seen
+/// by a pawn of the given color.
+const fn ranks_ahead(square: Square, color: Color) -> Bitboard {
+    let rank = square / 8;
+    match color {
+        Color::White if rank < 7 => u64::MAX << (8 * (rank + 1)),
+        Color::Black if rank > 0 => u64::MAX >> (8 * (8 - rank)),
+        _ => 0,
+    }
+}
+
+const fn passed_pawn_masks() -> [[Bitboard; 64]; COLOR_COUNT] {
+    let mut masks = [[0; 64]; COLOR_COUNT];
+    let mut square = 0;
+    while square < 64 {
+        let files = (FILEA << (square % 8)) | adjacent_files(square);
+        masks[0][square as usize] = files & ranks_ahead(square, Color::White);
+        masks[1][square as usize] = files & ranks_ahead(square, Color::Black);
+        square += 1;
+    }
+    masks
+}
+
 pub const INIT_W_ROOKS: Bitboard = 0b_1000_0001_u64;
 pub const INIT_W_KNIGHTS: Bitboard = 0b_0100_0010_u64;
 pub const INIT_W_BISHOPS: Bitboard = 0b_0010_0100_u64;
@@ -36,8 +91,98 @@ fn shift_right(n: u64, i: u8) -> Bitboard {
     n.checked_shr(u32::from(i)).unwrap_or(0)
 }
 
+/// A single step of a king or a knight. Shifting a bitboard in one of these directions drops any
+/// square which would otherwise wrap around onto the opposite edge of the board.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthNorthEast,
+    NorthEastEast,
+    SouthEastEast,
+    SouthSouthEast,
+    SouthSouthWest,
+    SouthWestWest,
+    NorthWestWest,
+    NorthNorthWest,
+}
+
+impl Direction {
+    /// The eight directions a king can step in.
+    pub const KING: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The eight directions a knight can jump in.
+    pub const KNIGHT: [Direction; 8] = [
+        Direction::NorthNorthEast,
+        Direction::NorthEastEast,
+        Direction::SouthEastEast,
+        Direction::SouthSouthEast,
+        Direction::SouthSouthWest,
+        Direction::SouthWestWest,
+        Direction::NorthWestWest,
+        Direction::NorthNorthWest,
+    ];
+
+    /// Returns the change in square index caused by a step in this direction.
+    pub fn offset(self) -> i8 {
+        match self {
+            Direction::North => NORTH,
+            Direction::NorthEast => NORTH + EAST,
+            Direction::East => EAST,
+            Direction::SouthEast => SOUTH + EAST,
+            Direction::South => SOUTH,
+            Direction::SouthWest => SOUTH + WEST,
+            Direction::West => WEST,
+            Direction::NorthWest => NORTH + WEST,
+            Direction::NorthNorthEast => NORTH + NORTH + EAST,
+            Direction::NorthEastEast => NORTH + EAST + EAST,
+            Direction::SouthEastEast => SOUTH + EAST + EAST,
+            Direction::SouthSouthEast => SOUTH + SOUTH + EAST,
+            Direction::SouthSouthWest => SOUTH + SOUTH + WEST,
+            Direction::SouthWestWest => SOUTH + WEST + WEST,
+            Direction::NorthWestWest => NORTH + WEST + WEST,
+            Direction::NorthNorthWest => NORTH + NORTH + WEST,
+        }
+    }
+
+    /// Returns the files from which a step in this direction would leave the board sideways.
+    fn edge_files(self) -> Bitboard {
+        match self {
+            Direction::North | Direction::South => 0,
+            Direction::NorthEast
+            | Direction::East
+            | Direction::SouthEast
+            | Direction::NorthNorthEast
+            | Direction::SouthSouthEast => FILEH,
+            Direction::SouthWest
+            | Direction::West
+            | Direction::NorthWest
+            | Direction::SouthSouthWest
+            | Direction::NorthNorthWest => FILEA,
+            Direction::NorthEastEast | Direction::SouthEastEast => FILEG | FILEH,
+            Direction::SouthWestWest | Direction::NorthWestWest => FILEA | FILEB,
+        }
+    }
+}
+
 pub trait Shift {
     fn shift(&self, n: i8) -> Bitboard;
+    fn shift_dir(self, dir: Direction) -> Bitboard;
 }
 
 impl Shift for Bitboard {
@@ -68,6 +213,16 @@ impl Shift for Bitboard {
             shift_right(*self, -n as u8)
         }
     }
+
+    fn shift_dir(self, dir: Direction) -> Bitboard {
+        let bb = self & !dir.edge_files();
+        let offset = dir.offset();
+        if offset > 0 {
+            shift_left(bb, offset.unsigned_abs())
+        } else {
+            shift_right(bb, offset.unsigned_abs())
+        }
+    }
 }
 
 pub trait GetBit {
@@ -128,14 +283,49 @@ impl Iterator for BitboardIterator {
     }
 }
 
+/// An iterator over the squares of the set bits of a bitboard, from `a1` to `h8`.
+pub struct SquareIterator {
+    inner: BitboardIterator,
+}
+
+impl Iterator for SquareIterator {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        self.inner.next().map(|(square, _)| square)
+    }
+}
+
 pub trait PieceItr {
     fn iter(&self) -> BitboardIterator;
+    fn squares(&self) -> SquareIterator;
 }
 
 impl PieceItr for Bitboard {
     fn iter(&self) -> BitboardIterator {
         BitboardIterator { bb: *self }
     }
+
+    fn squares(&self) -> SquareIterator {
+        SquareIterator { inner: self.iter() }
+    }
+}
+
+pub trait PopCount {
+    fn popcount(&self) -> u32;
+    fn is_empty(&self) -> bool;
+}
+
+impl PopCount for Bitboard {
+    #[inline]
+    fn popcount(&self) -> u32 {
+        self.count_ones()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        *self == 0
+    }
 }
 
 pub trait New {
@@ -160,6 +350,16 @@ mod tests {
     use super::*;
     use crate::chess_move::{NORTH, WEST};
 
+    #[test]
+    fn iterates_over_squares() {
+        assert_eq!(
+            RANK1.squares().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert_eq!(0.squares().count(), 0);
+        assert_eq!((1 << 9 | 1 << 63).squares().collect::<Vec<_>>(), [9, 63]);
+    }
+
     #[test]
     fn adds_piece_eight_rank() {
         let b1: Bitboard = 0;
@@ -199,6 +399,21 @@ mod tests {
         assert_eq!(u8::from(b2), 1);
     }
 
+    #[test]
+    fn file_mask_covers_file() {
+        assert_eq!(file_mask(0), FILEA);
+        assert_eq!(file_mask(63), FILEH);
+        assert_eq!(file_mask(28), FILEE);
+    }
+
+    #[test]
+    fn popcount_counts_set_bits() {
+        assert_eq!(RANK1.popcount(), 8);
+        assert_eq!(Bitboard::empty().popcount(), 0);
+        assert!(Bitboard::empty().is_empty());
+        assert!(!FILEA.is_empty());
+    }
+
     #[test]
     fn left_overflow_goes_to_zero() {
         let b: Bitboard = 0b0000_1000u64;
@@ -219,4 +434,35 @@ mod tests {
         let s = b.shift(-64);
         assert_eq!(s, 0);
     }
+
+    #[test]
+    fn shifting_east_does_not_wrap() {
+        assert_eq!(FILEA.shift_dir(Direction::East), FILEB);
+        assert_eq!(FILEH.shift_dir(Direction::East), 0);
+        assert_eq!(FILEH.shift_dir(Direction::NorthEast), 0);
+        assert_eq!(FILEG.shift_dir(Direction::NorthEastEast), 0);
+        assert_eq!(FILEA.shift_dir(Direction::West), 0);
+        assert_eq!(FILEB.shift_dir(Direction::SouthWestWest), 0);
+        assert_eq!(RANK8.shift_dir(Direction::North), 0);
+        assert_eq!(RANK2.shift_dir(Direction::SouthSouthEast), 0);
+    }
+
+    #[test]
+    fn passed_pawn_mask_covers_front_and_adjacent_files() {
+        // A white pawn on e4 is passed if nothing stands on d5-d8, e5-e8, or f5-f8.
+        let e4 = 28;
+        let ahead = RANK5 | RANK6 | RANK7 | RANK8;
+        assert_eq!(
+            PASSED_PAWN_MASKS[Color::White][e4],
+            (FILED | FILEE | FILEF) & ahead
+        );
+        assert_eq!(
+            PASSED_PAWN_MASKS[Color::Black][e4],
+            (FILED | FILEE | FILEF) & (RANK1 | RANK2 | RANK3)
+        );
+        assert_eq!(front_span(e4 as u8, Color::White), FILEE & ahead);
+        assert_eq!(adjacent_files(0), FILEB);
+        assert_eq!(adjacent_files(63), FILEG);
+        assert_eq!(PASSED_PAWN_MASKS[Color::White][63], 0);
+    }
 }
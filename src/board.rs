@@ -1,15 +1,17 @@
-
+use std::fmt;
 
 use crate::bitboard::{
-    AddPiece, Bitboard, ClearBit, GetBit, New, Shift, INIT_W_BISHOPS, INIT_W_KING, INIT_W_KNIGHTS,
-    INIT_W_QUEEN, INIT_W_ROOKS, RANK1, RANK2, RANK7, RANK8,
+    AddPiece, Bitboard, ClearBit, GetBit, New, PieceItr, PopCount, Shift, INIT_W_BISHOPS,
+    INIT_W_KING, INIT_W_KNIGHTS, INIT_W_QUEEN, INIT_W_ROOKS, RANK1, RANK2, RANK7, RANK8,
 };
-use crate::chess_move::{Move, MoveType};
+use crate::chess_move::{CastleSide, Move, MoveType};
 use crate::piece::PieceType::Rook;
 use crate::piece::{Color, Piece, PieceType, COLOR_COUNT, PIECE_COUNT};
-use crate::square::Square;
-use crate::square::SquareIndex::{A1, A8, C1, C8, D1, D8, E1, E8, F1, F8, G1, G8, H1, H8};
+use crate::square::{rank_file_to_index, Square};
 
+/// The phase, summed over the non-pawn material of both sides, at or below which a position is
+/// considered to be an endgame.
+const ENDGAME_PHASE: isize = 10;
 
 #[derive(Copy, Clone)]
 pub struct BoardState {
@@ -18,26 +20,30 @@ pub struct BoardState {
     pub castling_rights: Castle,
     pub en_passant: Option<Square>,
     pub half_move: u8,
-    pub full_move: u8,
+    pub full_move: u16,
 }
 
 impl BoardState {
     #[inline]
+    #[must_use]
     pub fn bb(&self, color: Color, piece: PieceType) -> Bitboard {
         self.position.bb(piece, color)
     }
 
     #[inline]
+    #[must_use]
     pub fn bb_for_color(&self, color: Color) -> Bitboard {
         self.position.bb_for_color(color)
     }
 
     #[inline]
+    #[must_use]
     pub fn bb_pieces(&self, piece: PieceType) -> Bitboard {
         self.position.bb_for_piece(piece)
     }
 
     #[inline]
+    #[must_use]
     pub fn bb_all(&self) -> Bitboard {
         self.position.bb_for_color(Color::White) | self.position.bb_for_color(Color::Black)
     }
@@ -64,16 +70,120 @@ impl BoardState {
     }
 
     #[inline]
+    #[must_use]
     pub fn type_on(&self, square: Square) -> Option<PieceType> {
         self.position.type_on(square)
     }
 
+    #[inline]
+    #[must_use]
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.position.king_square(color)
+    }
+
     #[inline]
     #[allow(dead_code)]
+    #[must_use]
     pub fn color_on(&self, square: Square) -> Option<Color> {
         self.position.color_on(square)
     }
 
+    /// Returns every piece of the given color along with its square, see `Position::pieces_of`.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn pieces_of(&self, color: Color) -> impl Iterator<Item = (Square, PieceType)> {
+        self.position.pieces_of(color)
+    }
+
+    /// Returns the total value in centipawns of the given color's pieces, kings included.
+    #[inline]
+    #[must_use]
+    pub fn material(&self, color: Color) -> isize {
+        self.position.material[color]
+    }
+
+    /// Returns the total value in centipawns of the given color's pieces once all non-pawn
+    /// material has been traded off, kings included.
+    #[inline]
+    #[must_use]
+    pub fn endgame_material(&self, color: Color) -> isize {
+        self.position.endgame_material[color]
+    }
+
+    /// Returns the sum of the phase weights of all pieces on the board, which falls as non-pawn
+    /// material is traded off.
+    #[inline]
+    #[must_use]
+    pub fn phase(&self) -> isize {
+        self.position.phase
+    }
+
+    /// Returns whether or not the position should be treated as an endgame, which is the case when
+    /// neither side has a queen or when little non-pawn material is left on the board, such as
+    /// the queens and a minor piece each.
+    #[must_use]
+    pub fn is_endgame(&self) -> bool {
+        self.bb_pieces(PieceType::Queen).is_empty() || self.phase() <= ENDGAME_PHASE
+    }
+
+    /// Returns whether or not the side to move has any knights, bishops, rooks, or queens.
+    #[must_use]
+    pub fn side_to_move_has_non_pawn_material(&self) -> bool {
+        let us = self.active_player;
+        self.bb(us, PieceType::Knight)
+            | self.bb(us, PieceType::Bishop)
+            | self.bb(us, PieceType::Rook)
+            | self.bb(us, PieceType::Queen)
+            != 0
+    }
+
+    /// Checks that the position is internally consistent, which is useful when testing code which
+    /// modifies positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the piece and color bitboards disagree or overlap, if either side does not have
+    /// exactly one king, if a pawn stands on the first or last rank, or if the en passant square
+    /// is not on the expected rank.
+    #[allow(dead_code)]
+    pub fn assert_invariants(&self) {
+        let mut pieces: Bitboard = 0;
+        for piece in PieceType::iterator() {
+            let bb = self.bb_pieces(*piece);
+            assert_eq!(pieces & bb, 0, "{piece:?} overlap another piece type");
+            pieces |= bb;
+        }
+
+        let white = self.bb_for_color(Color::White);
+        let black = self.bb_for_color(Color::Black);
+        assert_eq!(white & black, 0, "white and black pieces overlap");
+        assert_eq!(pieces, white | black, "piece and color bitboards disagree");
+
+        for color in Color::iterator() {
+            assert_eq!(
+                self.bb(*color, PieceType::King).popcount(),
+                1,
+                "{color:?} must have exactly one king"
+            );
+        }
+
+        assert_eq!(
+            self.bb_pieces(PieceType::Pawn) & (RANK1 | RANK8),
+            0,
+            "pawns cannot stand on the first or last rank"
+        );
+
+        if let Some(square) = self.en_passant {
+            let rank = (!self.active_player).en_passant_rank();
+            assert_ne!(
+                Bitboard::for_square(square) & rank,
+                0,
+                "en passant square is on the wrong rank"
+            );
+        }
+    }
+
+    #[must_use]
     pub fn clone_with_move(&self, mv: Move) -> BoardState {
         let mut new_pos = *self;
         new_pos.make_move(mv);
@@ -88,6 +198,18 @@ impl BoardState {
         let kind = self.position.type_on(mv.from).unwrap();
         let us = self.active_player;
 
+        // Promotions are pawn moves and en passant is a capture, so both reset the clock, while
+        // castling is a king move and does not.
+        if kind == PieceType::Pawn || mv.is_capture() {
+            self.half_move = 0;
+        } else {
+            self.half_move = self.half_move.saturating_add(1);
+        }
+
+        if us == Color::Black {
+            self.full_move = self.full_move.saturating_add(1);
+        }
+
         if kind == PieceType::King {
             self.castling_rights.remove_rights(us);
         }
@@ -106,10 +228,7 @@ impl BoardState {
             self.make_rook_move(mv);
         }
 
-        let ep_offset: i8 = match us {
-            Color::White => 8,
-            Color::Black => -8,
-        };
+        let ep_offset = us.pawn_push_offset();
 
         if mv.kind == MoveType::Quiet {
             self.remove_piece(kind, us, mv.from);
@@ -143,10 +262,8 @@ impl BoardState {
     }
 
     fn make_double_push(&mut self, mv: &Move) {
-        match self.active_player {
-            Color::White => self.en_passant = Some(mv.to - 8),
-            Color::Black => self.en_passant = Some(mv.to + 8),
-        }
+        let offset = self.active_player.pawn_push_offset();
+        self.en_passant = Some((mv.to as i8 - offset) as u8);
     }
 
     fn capture(&mut self, mv: Move, active: Color) {
@@ -158,56 +275,39 @@ impl BoardState {
     }
 
     fn capture_rook(&mut self, mv: Move, active: Color) {
-        match active {
-            Color::White => {
-                if mv.to == H8 as u8 {
-                    self.castling_rights.black_king = false;
-                } else if mv.to == A8 as u8 {
-                    self.castling_rights.black_queen = false;
-                }
-            }
-            Color::Black => {
-                if mv.to == H1 as u8 {
-                    self.castling_rights.white_king = false;
-                } else if mv.to == A1 as u8 {
-                    self.castling_rights.white_queen = false;
-                }
+        for side in &CastleSide::ALL {
+            if mv.to == side.squares(!active).rook_from {
+                self.castling_rights.remove(!active, *side);
             }
         }
     }
 
     fn make_rook_move(&mut self, mv: Move) {
-        if self.active_player == Color::White {
-            if mv.from == H1 as u8 {
-                self.castling_rights.white_king = false;
-            }
-            if mv.from == A1 as u8 {
-                self.castling_rights.white_queen = false;
-            }
-        } else {
-            if mv.from == H8 as u8 {
-                self.castling_rights.black_king = false;
-            }
-            if mv.from == A8 as u8 {
-                self.castling_rights.black_queen = false;
+        for side in &CastleSide::ALL {
+            if mv.from == side.squares(self.active_player).rook_from {
+                self.castling_rights.remove(self.active_player, *side);
             }
         }
     }
 
     #[allow(dead_code)]
+    #[must_use]
     pub fn empty() -> BoardState {
         let position = Position::empty();
         BoardState {
             position,
             active_player: Color::White,
-            castling_rights: Castle::default(),
+            castling_rights: Castle::none(),
             en_passant: None,
             half_move: 0,
             full_move: 0,
         }
     }
+}
 
-    pub fn default() -> BoardState {
+/// The standard starting position, with white to move.
+impl Default for BoardState {
+    fn default() -> Self {
         BoardState {
             position: Position::default(),
             active_player: Color::White,
@@ -219,6 +319,37 @@ impl BoardState {
     }
 }
 
+/// Displays the board as an 8x8 grid of pieces with rank 8 at the top, using uppercase letters
+/// for white pieces, lowercase letters for black pieces, and `.` for empty squares. The board is
+/// followed by a line naming the player to move.
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in (0..8).rev() {
+            for file in 0..8 {
+                let square = rank_file_to_index(rank, file);
+                let c = match (self.type_on(square), self.color_on(square)) {
+                    (Some(piece), Some(Color::White)) => piece.to_char().to_ascii_uppercase(),
+                    (Some(piece), _) => piece.to_char(),
+                    (None, _) => '.',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+
+        match self.active_player {
+            Color::White => write!(f, "White to move"),
+            Color::Black => write!(f, "Black to move"),
+        }
+    }
+}
+
+impl fmt::Debug for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{self}")
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Castle {
     pub white_king: bool,
@@ -241,6 +372,26 @@ impl Castle {
         }
     }
 
+    /// Returns whether the given color may still castle towards the given side.
+    pub fn allows(self, color: Color, side: CastleSide) -> bool {
+        match (color, side) {
+            (Color::White, CastleSide::King) => self.white_king,
+            (Color::White, CastleSide::Queen) => self.white_queen,
+            (Color::Black, CastleSide::King) => self.black_king,
+            (Color::Black, CastleSide::Queen) => self.black_queen,
+        }
+    }
+
+    /// Removes the right of the given color to castle towards the given side.
+    pub fn remove(&mut self, color: Color, side: CastleSide) {
+        match (color, side) {
+            (Color::White, CastleSide::King) => self.white_king = false,
+            (Color::White, CastleSide::Queen) => self.white_queen = false,
+            (Color::Black, CastleSide::King) => self.black_king = false,
+            (Color::Black, CastleSide::Queen) => self.black_queen = false,
+        }
+    }
+
     pub fn default() -> Castle {
         Castle {
             white_king: true,
@@ -249,12 +400,29 @@ impl Castle {
             black_queen: true,
         }
     }
+
+    /// Returns castling rights in which neither player may castle to either side.
+    pub fn none() -> Castle {
+        Castle {
+            white_king: false,
+            white_queen: false,
+            black_king: false,
+            black_queen: false,
+        }
+    }
 }
 
+/// The placement of pieces on the board. The opening and endgame material of each side and the
+/// phase of the game are kept up to date as pieces are added and removed, so that evaluation does
+/// not need to count them. Moves are made on a copy of the position, so nothing has to be restored
+/// to unmake one.
 #[derive(Copy, Clone)]
 pub struct Position {
     pieces_bb: [Bitboard; PIECE_COUNT],
     colors_bb: [Bitboard; COLOR_COUNT],
+    material: [isize; COLOR_COUNT],
+    endgame_material: [isize; COLOR_COUNT],
+    phase: isize,
 }
 
 impl Position {
@@ -273,28 +441,72 @@ impl Position {
         self.pieces_bb[piece]
     }
 
+    /// Returns the square of the given color's king, regardless of which player is to move, or
+    /// `None` if that color has no king.
+    #[inline]
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.bb(PieceType::King, color)
+            .iter()
+            .next()
+            .map(|(square, _)| square)
+    }
+
+    /// Returns every piece of the given color along with its square, in a single pass over the
+    /// pieces of that color rather than one loop per piece type. Pieces are grouped by type.
+    #[allow(dead_code)]
+    pub fn pieces_of(&self, color: Color) -> impl Iterator<Item = (Square, PieceType)> {
+        let pieces = self.pieces_bb;
+        let ours = self.colors_bb[color];
+        PieceType::iterator().flat_map(move |piece| {
+            (pieces[*piece] & ours)
+                .squares()
+                .map(move |square| (square, *piece))
+        })
+    }
+
     pub fn add_piece(&mut self, c: char, rank: u8, file: u8) {
         let piece = Piece::convert_char_to_piece(c);
         let color = Piece::convert_char_to_color(c);
-        self.pieces_bb[piece] = self.pieces_bb[piece].add_piece(rank, file);
-        self.colors_bb[color] = self.colors_bb[color].add_piece(rank, file);
+        self.add(piece, color, rank_file_to_index(rank, file));
     }
 
     pub fn add(&mut self, piece: PieceType, color: Color, square: Square) {
+        debug_assert!(
+            self.type_on(square).is_none(),
+            "cannot add {:?} {:?} to occupied square {}",
+            color,
+            piece,
+            square
+        );
         self.pieces_bb[piece] = self.pieces_bb[piece].add_at_square(square);
         self.colors_bb[color] = self.colors_bb[color].add_at_square(square);
+        self.material[color] += piece.material_value();
+        self.endgame_material[color] += piece.endgame_value();
+        self.phase += piece.phase();
     }
 
     pub fn remove(&mut self, piece: PieceType, color: Color, square: Square) {
+        debug_assert!(
+            self.bb(piece, color) & Bitboard::for_square(square) != 0,
+            "cannot remove {:?} {:?} missing from square {}",
+            color,
+            piece,
+            square
+        );
         self.pieces_bb[piece] = self.pieces_bb[piece].clear_bit(square);
         self.colors_bb[color] = self.colors_bb[color].clear_bit(square);
+        self.material[color] -= piece.material_value();
+        self.endgame_material[color] -= piece.endgame_value();
+        self.phase -= piece.phase();
     }
 
     pub fn castle(&mut self, kind: MoveType, color: Color) {
-        match kind {
-            MoveType::CastleKing => self.castle_king(color),
-            MoveType::CastleQueen => self.castle_queen(color),
-            _ => {}
+        if let Some(side) = CastleSide::from_move_type(kind) {
+            let squares = side.squares(color);
+            self.remove(PieceType::King, color, squares.king_from);
+            self.remove(PieceType::Rook, color, squares.rook_from);
+            self.add(PieceType::King, color, squares.king_to);
+            self.add(PieceType::Rook, color, squares.rook_to);
         }
     }
 
@@ -306,40 +518,6 @@ impl Position {
         self.add(kind, active, mv.to);
     }
 
-    fn castle_king(&mut self, color: Color) {
-        match color {
-            Color::White => {
-                self.remove(PieceType::King, color, E1 as u8);
-                self.remove(PieceType::Rook, color, H1 as u8);
-                self.add(PieceType::King, color, G1 as u8);
-                self.add(PieceType::Rook, color, F1 as u8);
-            }
-            Color::Black => {
-                self.remove(PieceType::King, color, E8 as u8);
-                self.remove(PieceType::Rook, color, H8 as u8);
-                self.add(PieceType::King, color, G8 as u8);
-                self.add(PieceType::Rook, color, F8 as u8);
-            }
-        }
-    }
-
-    fn castle_queen(&mut self, color: Color) {
-        match color {
-            Color::White => {
-                self.remove(PieceType::King, color, E1 as u8);
-                self.remove(PieceType::Rook, color, A1 as u8);
-                self.add(PieceType::King, color, C1 as u8);
-                self.add(PieceType::Rook, color, D1 as u8);
-            }
-            Color::Black => {
-                self.remove(PieceType::King, color, E8 as u8);
-                self.remove(PieceType::Rook, color, A8 as u8);
-                self.add(PieceType::King, color, C8 as u8);
-                self.add(PieceType::Rook, color, D8 as u8);
-            }
-        }
-    }
-
     pub fn type_on(&self, square: Square) -> Option<PieceType> {
         let piece_bb = Bitboard::for_square(square);
         for (i, bb) in self.pieces_bb.iter().enumerate() {
@@ -381,10 +559,15 @@ impl Position {
         colors_bb[Color::White] = RANK1 | RANK2;
         colors_bb[Color::Black] = RANK7 | RANK8;
 
-        Position {
+        let mut position = Position {
             pieces_bb,
             colors_bb,
-        }
+            material: [0; COLOR_COUNT],
+            endgame_material: [0; COLOR_COUNT],
+            phase: 0,
+        };
+        position.count_material();
+        position
     }
 
     pub fn empty() -> Position {
@@ -393,6 +576,242 @@ impl Position {
         Position {
             pieces_bb,
             colors_bb,
+            material: [0; COLOR_COUNT],
+            endgame_material: [0; COLOR_COUNT],
+            phase: 0,
         }
     }
+
+    /// Counts the material and phase from scratch, for positions which are set up directly from
+    /// bitboards rather than by adding pieces one at a time.
+    fn count_material(&mut self) {
+        self.material = [0; COLOR_COUNT];
+        self.endgame_material = [0; COLOR_COUNT];
+        self.phase = 0;
+        for piece in PieceType::iterator() {
+            for color in Color::iterator() {
+                for _ in self.bb(*piece, *color).iter() {
+                    self.material[*color] += piece.material_value();
+                    self.endgame_material[*color] += piece.endgame_value();
+                    self.phase += piece.phase();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_fen;
+    use crate::square::SquareIndex::{
+        self, A1, A2, A7, A8, B2, B7, B8, C6, D5, D6, E1, E4, E5, E7, F3, G1, G2,
+    };
+
+    #[test]
+    fn displays_starting_position() {
+        let expected = "rnbqkbnr\n\
+                        pppppppp\n\
+                        ........\n\
+                        ........\n\
+                        ........\n\
+                        ........\n\
+                        PPPPPPPP\n\
+                        RNBQKBNR\n\
+                        White to move";
+        assert_eq!(BoardState::default().to_string(), expected);
+    }
+
+    #[test]
+    fn finds_both_kings() {
+        let pos = parse_fen("8/8/2k5/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(pos.king_square(Color::White), Some(G1 as Square));
+        assert_eq!(pos.king_square(Color::Black), Some(C6 as Square));
+        assert_eq!(Position::empty().king_square(Color::Black), None);
+    }
+
+    #[test]
+    fn tracks_material_and_phase_incrementally() {
+        let mut pos = parse_fen("r3k3/1P6/8/3p4/4P3/8/6p1/4K3 w - - 0 1").unwrap();
+        let moves = [
+            (E4 as u8, D5 as u8, MoveType::Capture),
+            (G2 as u8, G1 as u8, MoveType::KnightPromotion),
+            (B7 as u8, A8 as u8, MoveType::QueenPromotionCapture),
+        ];
+        for &(from, to, kind) in &moves {
+            pos.make_move(Move { from, to, kind });
+
+            let mut counted = pos.position;
+            counted.count_material();
+            assert_eq!(pos.material(Color::White), counted.material[Color::White]);
+            assert_eq!(pos.material(Color::Black), counted.material[Color::Black]);
+            assert_eq!(
+                pos.endgame_material(Color::White),
+                counted.endgame_material[Color::White]
+            );
+            assert_eq!(
+                pos.endgame_material(Color::Black),
+                counted.endgame_material[Color::Black]
+            );
+            assert_eq!(pos.phase(), counted.phase);
+        }
+        assert_eq!(pos.material(Color::White), 350 + 100 + 800);
+        assert_eq!(pos.material(Color::Black), 350 + 300);
+        assert_eq!(pos.phase(), 4 + 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "occupied square")]
+    fn adding_to_occupied_square_panics() {
+        let mut pos = BoardState::default();
+        pos.add(PieceType::Knight, Color::White, SquareIndex::E2 as Square);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "missing from square")]
+    fn removing_missing_piece_panics() {
+        let mut pos = BoardState::default();
+        pos.remove_piece(PieceType::Knight, Color::Black, SquareIndex::E2 as Square);
+    }
+
+    #[test]
+    fn detects_non_pawn_material() {
+        assert!(BoardState::default().side_to_move_has_non_pawn_material());
+
+        let pawn_endgame = parse_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap();
+        assert!(!pawn_endgame.side_to_move_has_non_pawn_material());
+
+        let black_has_rook = parse_fen("r3k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap();
+        assert!(!black_has_rook.side_to_move_has_non_pawn_material());
+    }
+
+    #[test]
+    fn updates_move_counters() {
+        let mut pos = BoardState::default();
+        let knight = Move {
+            from: G1 as u8,
+            to: F3 as u8,
+            kind: MoveType::Quiet,
+        };
+        pos.make_move(knight);
+        assert_eq!((pos.half_move, pos.full_move), (1, 1));
+
+        let pawn = Move {
+            from: E7 as u8,
+            to: E5 as u8,
+            kind: MoveType::Quiet,
+        };
+        pos.make_move(pawn);
+        assert_eq!((pos.half_move, pos.full_move), (0, 2));
+    }
+
+    #[test]
+    fn only_pawn_moves_and_captures_reset_half_move_clock() {
+        let half_move_after = |fen: &str, from: SquareIndex, to: SquareIndex, kind: MoveType| {
+            let mut pos = parse_fen(fen).unwrap();
+            pos.make_move(Move {
+                from: from as u8,
+                to: to as u8,
+                kind,
+            });
+            pos.half_move
+        };
+
+        let castle = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 7 20";
+        assert_eq!(half_move_after(castle, E1, G1, MoveType::CastleKing), 8);
+
+        let promotion = "1n2k3/P7/8/8/8/8/8/4K3 w - - 7 20";
+        assert_eq!(
+            half_move_after(promotion, A7, A8, MoveType::QueenPromotion),
+            0
+        );
+        let kind = MoveType::KnightPromotionCapture;
+        assert_eq!(half_move_after(promotion, A7, B8, kind), 0);
+
+        let en_passant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 20";
+        assert_eq!(
+            half_move_after(en_passant, E5, D6, MoveType::EnPassantCapture),
+            0
+        );
+    }
+
+    #[test]
+    fn promoting_to_rook_on_corner_does_not_restore_castling() {
+        let rights_after = |fen: &str, from: SquareIndex, to: SquareIndex, kind: MoveType| {
+            let mut pos = parse_fen(fen).unwrap();
+            pos.make_move(Move {
+                from: from as u8,
+                to: to as u8,
+                kind,
+            });
+            pos.castling_rights
+        };
+
+        // The original rooks on a1 and a8 have long since left.
+        let kind = MoveType::RookPromotion;
+        let rights = rights_after("4k2r/8/8/8/8/8/p7/4K2R b Kk - 0 1", A2, A1, kind);
+        assert!(rights.white_king && !rights.white_queen);
+        assert!(rights.black_king && !rights.black_queen);
+        let rights = rights_after("4k2r/P7/8/8/8/8/8/4K2R w Kk - 0 1", A7, A8, kind);
+        assert!(rights.white_king && !rights.white_queen);
+        assert!(rights.black_king && !rights.black_queen);
+
+        // Capturing the rook on its home square by promotion takes away its castling right.
+        let kind = MoveType::RookPromotionCapture;
+        let rights = rights_after("4k3/8/8/8/8/8/1p6/R3K2R b KQ - 0 1", B2, A1, kind);
+        assert!(rights.white_king && !rights.white_queen);
+    }
+
+    #[test]
+    fn lists_pieces_of_each_color() {
+        let pos = BoardState::default();
+        let mut white: Vec<_> = pos.pieces_of(Color::White).collect();
+        white.sort_unstable_by_key(|(square, _)| *square);
+        assert_eq!(white.len(), 16);
+        let back_rank = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+            PieceType::King,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+        ];
+        for (i, (square, piece)) in white.iter().enumerate() {
+            assert_eq!(usize::from(*square), i);
+            let expected = back_rank.get(i).copied().unwrap_or(PieceType::Pawn);
+            assert_eq!(*piece, expected);
+        }
+
+        let black: Vec<_> = pos.pieces_of(Color::Black).collect();
+        assert_eq!(black.len(), 16);
+        assert!(black.iter().all(|(square, _)| *square >= 48));
+    }
+
+    #[test]
+    fn starting_position_is_not_endgame() {
+        assert!(!BoardState::default().is_endgame());
+    }
+
+    #[test]
+    fn queenless_position_is_endgame() {
+        let pos =
+            parse_fen("r1b1k2r/pppp1ppp/2n2n2/8/8/2N2N2/PPPP1PPP/R1B1K2R w KQkq - 0 1").unwrap();
+        assert!(pos.is_endgame());
+    }
+
+    #[test]
+    fn queens_with_little_material_is_endgame() {
+        let pos = parse_fen("3qk3/pppp4/8/8/8/8/4PPPP/2N1K1Q1 w - - 0 1").unwrap();
+        assert!(pos.is_endgame());
+    }
+
+    #[test]
+    fn queens_and_rooks_is_not_endgame() {
+        let pos = parse_fen("r2qk3/pppp4/8/8/8/8/4PPPP/4K1QR w - - 0 1").unwrap();
+        assert!(!pos.is_endgame());
+    }
 }
@@ -2,7 +2,10 @@ use std::cmp::Ordering;
 use std::ops::Neg;
 use std::slice::Iter;
 
-use crate::piece::PieceType;
+use crate::bitboard::Bitboard;
+use crate::piece::{Color, PieceType};
+use crate::square::Square;
+use crate::square::SquareIndex::{A1, C1, D1, E1, F1, G1, H1};
 
 pub const NORTH: i8 = 8;
 pub const EAST: i8 = 1;
@@ -34,6 +37,110 @@ pub enum MoveType {
     Null,
 }
 
+/// The kind of a move with the promoted piece set aside, so that code which only cares whether a
+/// move is, say, a promotion does not have to list every promotion variant.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveCategory {
+    Quiet,
+    Capture,
+    EnPassant,
+    Promotion,
+    PromotionCapture,
+    Castle,
+    Null,
+}
+
+/// Every move type, ordered by its value in the compact 16-bit move encoding.
+static MOVE_TYPES: [MoveType; 14] = [
+    MoveType::Capture,
+    MoveType::EnPassantCapture,
+    MoveType::KnightPromotion,
+    MoveType::BishopPromotion,
+    MoveType::RookPromotion,
+    MoveType::QueenPromotion,
+    MoveType::KnightPromotionCapture,
+    MoveType::BishopPromotionCapture,
+    MoveType::RookPromotionCapture,
+    MoveType::QueenPromotionCapture,
+    MoveType::Quiet,
+    MoveType::CastleKing,
+    MoveType::CastleQueen,
+    MoveType::Null,
+];
+
+/// The side of the board towards which a player castles.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CastleSide {
+    King,
+    Queen,
+}
+
+/// The squares which the king and rook move between when castling. These are the only place
+/// where castling squares are defined, and are used to generate, check, and make castling moves.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CastleSquares {
+    pub king_from: Square,
+    pub king_to: Square,
+    pub rook_from: Square,
+    pub rook_to: Square,
+}
+
+impl CastleSide {
+    pub const ALL: [CastleSide; 2] = [CastleSide::King, CastleSide::Queen];
+
+    /// Returns the side castled towards by a castling move type, or `None` for any other type.
+    pub fn from_move_type(kind: MoveType) -> Option<CastleSide> {
+        match kind {
+            MoveType::CastleKing => Some(CastleSide::King),
+            MoveType::CastleQueen => Some(CastleSide::Queen),
+            _ => None,
+        }
+    }
+
+    pub fn move_type(self) -> MoveType {
+        match self {
+            CastleSide::King => MoveType::CastleKing,
+            CastleSide::Queen => MoveType::CastleQueen,
+        }
+    }
+
+    /// Returns the squares the king and rook of the given color move between when castling
+    /// towards this side.
+    pub fn squares(self, color: Color) -> CastleSquares {
+        let back_rank = match color {
+            Color::White => 0,
+            Color::Black => 56,
+        };
+        let (king_to, rook_from, rook_to) = match self {
+            CastleSide::King => (G1, H1, F1),
+            CastleSide::Queen => (C1, A1, D1),
+        };
+        CastleSquares {
+            king_from: E1 as u8 + back_rank,
+            king_to: king_to as u8 + back_rank,
+            rook_from: rook_from as u8 + back_rank,
+            rook_to: rook_to as u8 + back_rank,
+        }
+    }
+}
+
+impl CastleSquares {
+    /// Returns the squares between the king and the rook, all of which must be empty to castle.
+    pub fn empty_squares(self) -> Bitboard {
+        squares_between(self.king_from, self.rook_from)
+    }
+
+    /// Returns the squares the king passes through and lands on, none of which may be attacked.
+    pub fn king_path(self) -> Bitboard {
+        squares_between(self.king_from, self.king_to) | 1 << self.king_to
+    }
+}
+
+/// Returns the squares strictly between two squares on the same rank.
+fn squares_between(a: Square, b: Square) -> Bitboard {
+    (a.min(b) + 1..a.max(b)).fold(0, |bb, square| bb | 1 << square)
+}
+
 #[derive(Clone, Copy)]
 pub enum PromotionType {
     Push,
@@ -59,6 +166,7 @@ fn rank_file_to_algebra(rank: u8, file: u8) -> String {
 }
 
 impl Move {
+    #[must_use]
     pub fn null() -> Move {
         Move {
             to: 0,
@@ -67,6 +175,30 @@ impl Move {
         }
     }
 
+    /// Pack the move into 16 bits: the origin square in the lowest 6 bits, the destination square
+    /// in the next 6 bits, and the move type in the highest 4 bits.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn to_u16(self) -> u16 {
+        u16::from(self.from) | u16::from(self.to) << 6 | (self.kind as u16) << 12
+    }
+
+    /// Unpack a move produced by `to_u16`. Move type bits which do not correspond to any move
+    /// type decode as a null move type.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn from_u16(bits: u16) -> Move {
+        Move {
+            from: (bits & 0x3F) as u8,
+            to: (bits >> 6 & 0x3F) as u8,
+            kind: MOVE_TYPES
+                .get(usize::from(bits >> 12))
+                .copied()
+                .unwrap_or(MoveType::Null),
+        }
+    }
+
+    #[must_use]
     pub fn to_algebraic(self) -> String {
         let to_rank = self.to / 8;
         let to_file = self.to % 8;
@@ -78,51 +210,47 @@ impl Move {
         s.push_str(rank_file_to_algebra(from_rank, from_file).as_str());
         s.push_str(rank_file_to_algebra(to_rank, to_file).as_str());
 
-        if self.is_promotion() || self.is_promotion_capture() {
-            match self.promoted_piece().unwrap() {
-                PieceType::Rook => s.push('r'),
-                PieceType::Knight => s.push('n'),
-                PieceType::Bishop => s.push('b'),
-                PieceType::Queen => s.push('q'),
-                _ => {}
-            }
+        if let Some(piece) = self.promoted_piece() {
+            s.push(piece.to_char());
         }
 
         s
     }
 
+    #[must_use]
     pub fn is_double_pawn_push(&self) -> bool {
         ((self.to as i8) - (self.from as i8)).abs() == 16
     }
 
+    #[must_use]
     pub fn is_promotion_capture(&self) -> bool {
-        self.kind == MoveType::KnightPromotionCapture
-            || self.kind == MoveType::BishopPromotionCapture
-            || self.kind == MoveType::RookPromotionCapture
-            || self.kind == MoveType::QueenPromotionCapture
+        self.kind.category() == MoveCategory::PromotionCapture
     }
 
+    #[must_use]
     pub fn is_promotion(&self) -> bool {
-        self.kind == MoveType::KnightPromotion
-            || self.kind == MoveType::BishopPromotion
-            || self.kind == MoveType::RookPromotion
-            || self.kind == MoveType::QueenPromotion
+        self.kind.category() == MoveCategory::Promotion
     }
 
+    #[must_use]
     pub fn is_en_passant_capture(&self) -> bool {
-        self.kind == MoveType::EnPassantCapture
+        self.kind.category() == MoveCategory::EnPassant
     }
 
+    #[must_use]
     pub fn is_castle(&self) -> bool {
-        self.kind == MoveType::CastleKing || self.kind == MoveType::CastleQueen
+        self.kind.category() == MoveCategory::Castle
     }
 
+    #[must_use]
     pub fn is_capture(&self) -> bool {
-        self.kind == MoveType::Capture
-            || self.kind == MoveType::EnPassantCapture
-            || self.is_promotion_capture()
+        matches!(
+            self.kind.category(),
+            MoveCategory::Capture | MoveCategory::EnPassant | MoveCategory::PromotionCapture
+        )
     }
 
+    #[must_use]
     pub fn promoted_piece(&self) -> Option<PieceType> {
         match self.kind {
             MoveType::RookPromotionCapture | MoveType::RookPromotion => Some(PieceType::Rook),
@@ -135,18 +263,25 @@ impl Move {
 }
 
 impl MoveType {
-    pub fn king_itr() -> Iter<'static, i8> {
-        static KING_MOVES: [i8; 8] = [
-            WEST,
-            NORTH + WEST,
-            NORTH,
-            NORTH + EAST,
-            EAST,
-            SOUTH + EAST,
-            SOUTH,
-            SOUTH + WEST,
-        ];
-        KING_MOVES.iter()
+    /// Returns the category of this move type. Every variant is listed, so that a new move type
+    /// cannot be added without deciding which category it belongs to.
+    #[must_use]
+    pub fn category(self) -> MoveCategory {
+        match self {
+            MoveType::Quiet => MoveCategory::Quiet,
+            MoveType::Capture => MoveCategory::Capture,
+            MoveType::EnPassantCapture => MoveCategory::EnPassant,
+            MoveType::KnightPromotion
+            | MoveType::BishopPromotion
+            | MoveType::RookPromotion
+            | MoveType::QueenPromotion => MoveCategory::Promotion,
+            MoveType::KnightPromotionCapture
+            | MoveType::BishopPromotionCapture
+            | MoveType::RookPromotionCapture
+            | MoveType::QueenPromotionCapture => MoveCategory::PromotionCapture,
+            MoveType::CastleKing | MoveType::CastleQueen => MoveCategory::Castle,
+            MoveType::Null => MoveCategory::Null,
+        }
     }
 
     pub fn promotion_itr() -> Iter<'static, MoveType> {
@@ -177,6 +312,7 @@ pub struct EvaledMove {
 }
 
 impl EvaledMove {
+    #[must_use]
     pub fn null(eval: isize) -> EvaledMove {
         EvaledMove {
             mv: Move::null(),
@@ -231,6 +367,118 @@ mod test {
         assert_eq!(s, "a2a3");
     }
 
+    #[test]
+    fn castle_squares_match_standard_chess() {
+        use crate::chess_move::{CastleSide, CastleSquares, MoveType};
+        use crate::piece::Color;
+        use crate::square::SquareIndex::*;
+
+        let squares = |king_from, king_to, rook_from, rook_to| CastleSquares {
+            king_from: king_from as u8,
+            king_to: king_to as u8,
+            rook_from: rook_from as u8,
+            rook_to: rook_to as u8,
+        };
+        let white_king = CastleSide::King.squares(Color::White);
+        let white_queen = CastleSide::Queen.squares(Color::White);
+        let black_king = CastleSide::King.squares(Color::Black);
+        let black_queen = CastleSide::Queen.squares(Color::Black);
+        assert_eq!(white_king, squares(E1, G1, H1, F1));
+        assert_eq!(white_queen, squares(E1, C1, A1, D1));
+        assert_eq!(black_king, squares(E8, G8, H8, F8));
+        assert_eq!(black_queen, squares(E8, C8, A8, D8));
+
+        assert_eq!(white_king.empty_squares(), 96);
+        assert_eq!(white_queen.empty_squares(), 14);
+        assert_eq!(black_king.empty_squares(), 6_917_529_027_641_081_856);
+        assert_eq!(black_queen.empty_squares(), 1_008_806_316_530_991_104);
+        assert_eq!(white_king.king_path(), 1 << 5 | 1 << 6);
+        assert_eq!(white_queen.king_path(), 1 << 2 | 1 << 3);
+        assert_eq!(black_king.king_path(), 1 << 61 | 1 << 62);
+        assert_eq!(black_queen.king_path(), 1 << 58 | 1 << 59);
+
+        for side in CastleSide::ALL.iter() {
+            assert_eq!(CastleSide::from_move_type(side.move_type()), Some(*side));
+        }
+        assert_eq!(CastleSide::from_move_type(MoveType::Quiet), None);
+    }
+
+    #[test]
+    fn compact_encoding_round_trips() {
+        use crate::chess_move::MoveType::{CastleKing, QueenPromotionCapture};
+        use crate::square::SquareIndex::{B7, C8, E1, G1};
+
+        let promotion_capture = Move {
+            from: B7 as u8,
+            to: C8 as u8,
+            kind: QueenPromotionCapture,
+        };
+        let castle = Move {
+            from: E1 as u8,
+            to: G1 as u8,
+            kind: CastleKing,
+        };
+        assert_eq!(
+            Move::from_u16(promotion_capture.to_u16()),
+            promotion_capture
+        );
+        assert_eq!(Move::from_u16(castle.to_u16()), castle);
+
+        for kind in super::MOVE_TYPES.iter() {
+            let m = Move {
+                from: 63,
+                to: 0,
+                kind: *kind,
+            };
+            assert_eq!(Move::from_u16(m.to_u16()), m);
+        }
+    }
+
+    #[test]
+    fn every_move_type_has_a_category() {
+        use crate::chess_move::MoveType::*;
+        use crate::chess_move::{MoveCategory, MoveType};
+
+        let expected = [
+            (Capture, MoveCategory::Capture),
+            (EnPassantCapture, MoveCategory::EnPassant),
+            (KnightPromotion, MoveCategory::Promotion),
+            (BishopPromotion, MoveCategory::Promotion),
+            (RookPromotion, MoveCategory::Promotion),
+            (QueenPromotion, MoveCategory::Promotion),
+            (KnightPromotionCapture, MoveCategory::PromotionCapture),
+            (BishopPromotionCapture, MoveCategory::PromotionCapture),
+            (RookPromotionCapture, MoveCategory::PromotionCapture),
+            (QueenPromotionCapture, MoveCategory::PromotionCapture),
+            (Quiet, MoveCategory::Quiet),
+            (CastleKing, MoveCategory::Castle),
+            (CastleQueen, MoveCategory::Castle),
+            (Null, MoveCategory::Null),
+        ];
+        assert_eq!(expected.len(), super::MOVE_TYPES.len());
+        for (kind, category) in expected.iter() {
+            assert_eq!(kind.category(), *category, "{:?}", kind);
+        }
+
+        for kind in super::MOVE_TYPES.iter() {
+            let m = Move {
+                from: 0,
+                to: 0,
+                kind: *kind,
+            };
+            assert_eq!(
+                m.promoted_piece().is_some(),
+                m.is_promotion() || m.is_promotion_capture()
+            );
+        }
+        for kind in MoveType::promotion_itr().chain(MoveType::promotion_capture_itr()) {
+            assert!(matches!(
+                kind.category(),
+                MoveCategory::Promotion | MoveCategory::PromotionCapture
+            ));
+        }
+    }
+
     use std::cmp::{max, min};
 
     use crate::chess_move::EvaledMove;
@@ -0,0 +1,135 @@
+/// The number of moves the remaining time is divided between when the time control does not say
+/// how many moves are left until the next time control.
+const DEFAULT_MOVES_TO_GO: u128 = 30;
+
+/// A chess clock setting, with all times given in milliseconds.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TimeControl {
+    /// The time on the clock at the start of the game, which is added again whenever
+    /// `moves_to_go` moves have been played.
+    pub base: u128,
+    /// The time added to the clock after every move.
+    pub increment: u128,
+    /// The number of moves to be played before the base time is added again, or `None` if the
+    /// base time has to last for the rest of the game.
+    pub moves_to_go: Option<u32>,
+}
+
+/// The state of a player's clock under a time control.
+pub struct Clock {
+    control: TimeControl,
+    remaining: u128,
+    moves_to_go: Option<u32>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            control,
+            remaining: control.base,
+            moves_to_go: control.moves_to_go,
+        }
+    }
+
+    /// Returns the time left on the clock.
+    pub fn remaining(&self) -> u128 {
+        self.remaining
+    }
+
+    /// Returns the time to spend on the next move: an equal share of the remaining time for each
+    /// move left until the next time control, plus most of the increment. No more than half of
+    /// the remaining time is ever used, so that a search which overruns its budget slightly
+    /// cannot run out the clock.
+    pub fn budget(&self) -> u128 {
+        let moves = self
+            .moves_to_go
+            .map_or(DEFAULT_MOVES_TO_GO, |moves| u128::from(moves.max(1)));
+        let budget = self.remaining / moves + self.control.increment * 3 / 4;
+        budget.min(self.remaining / 2)
+    }
+
+    /// Take the time spent on a move off the clock, and then add the increment, along with the
+    /// base time if the move completed the current time control.
+    pub fn spend(&mut self, elapsed: u128) {
+        self.remaining = self.remaining.saturating_sub(elapsed) + self.control.increment;
+        if let Some(moves) = self.moves_to_go {
+            if moves <= 1 {
+                self.remaining += self.control.base;
+                self.moves_to_go = self.control.moves_to_go;
+            } else {
+                self.moves_to_go = Some(moves - 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn budget_divides_remaining_time() {
+        let clock = Clock::new(TimeControl {
+            base: 60_000,
+            increment: 0,
+            moves_to_go: None,
+        });
+        assert_eq!(clock.budget(), 2_000);
+
+        let clock = Clock::new(TimeControl {
+            base: 60_000,
+            increment: 1_000,
+            moves_to_go: Some(20),
+        });
+        assert_eq!(clock.budget(), 3_750);
+
+        let clock = Clock::new(TimeControl {
+            base: 1_000,
+            increment: 0,
+            moves_to_go: Some(1),
+        });
+        assert_eq!(clock.budget(), 500);
+    }
+
+    #[test]
+    fn spending_adds_increment_and_next_time_control() {
+        let mut clock = Clock::new(TimeControl {
+            base: 10_000,
+            increment: 100,
+            moves_to_go: Some(2),
+        });
+        clock.spend(1_000);
+        assert_eq!(clock.remaining(), 9_100);
+        clock.spend(1_000);
+        assert_eq!(clock.remaining(), 18_200);
+        assert_eq!(clock.moves_to_go, Some(2));
+    }
+
+    #[test]
+    fn spending_each_budget_never_runs_out_the_clock() {
+        let mut clock = Clock::new(TimeControl {
+            base: 3_000,
+            increment: 0,
+            moves_to_go: None,
+        });
+        for _ in 0..200 {
+            let (remaining, budget) = (clock.remaining(), clock.budget());
+            assert_eq!(budget, remaining / 30);
+            clock.spend(budget);
+            assert_eq!(clock.remaining(), remaining - budget);
+        }
+        assert!(clock.remaining() > 0);
+    }
+
+    #[test]
+    fn overrunning_the_clock_leaves_only_the_increment() {
+        let mut clock = Clock::new(TimeControl {
+            base: 1_000,
+            increment: 50,
+            moves_to_go: None,
+        });
+        clock.spend(5_000);
+        assert_eq!(clock.remaining(), 50);
+        assert_eq!(clock.budget(), 25);
+    }
+}
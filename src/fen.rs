@@ -1,33 +1,134 @@
-
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 use crate::board::{BoardState, Castle, Position};
 use crate::piece::Color;
-use crate::square::{algebraic_to_square, Square};
-
+use crate::square::{rank_file_to_index, square_from_algebraic, square_to_algebraic, Square};
 
 pub fn parse_fen(fen: &str) -> Result<BoardState, String> {
     let mut s = fen.split_whitespace();
-    println!("FEN: {}", fen);
 
-    let position = parse_ranks(s.next().unwrap());
-    let active_color = parse_active_color(s.next().unwrap());
-    let castling_rights = parse_castling_rights(s.next().unwrap());
-    let en_passant = parse_en_passant(s.next().unwrap());
-    let half_move = parse_move(s.next().unwrap());
-    let full_move = parse_move(s.next().unwrap());
+    let mut field = |name: &str| {
+        s.next()
+            .ok_or_else(|| format!("FEN is missing the {name} field, is invalid"))
+    };
+    let position = parse_ranks(field("position")?);
+    let active_color = parse_active_color(field("active color")?);
+    let castling_rights = parse_castling_rights(field("castling")?);
+    let en_passant = parse_en_passant(field("en passant")?)?;
+    // The move counters are often left out, as in EPD, so they default to the starting values.
+    let half_move = s
+        .next()
+        .map_or(Ok(0), |field| parse_move(field, "halfmove clock"))?;
+    let full_move = s
+        .next()
+        .map_or(Ok(1), |field| parse_move(field, "fullmove number"))?;
 
     let board_state = BoardState {
-        position: position.unwrap(),
-        active_player: active_color.unwrap(),
+        position: position?,
+        active_player: active_color?,
         castling_rights,
         en_passant,
-        half_move,
+        half_move: u8::try_from(half_move).unwrap_or(u8::MAX),
         full_move,
     };
 
     Ok(board_state)
 }
 
+/// Parse an EPD (Extended Position Description) line, which is the first four fields of a FEN
+/// string followed by a list of operations such as `bm Qxf7+; id "WAC.001";`. EPD omits the move
+/// counters, so the halfmove clock is set to 0 and the fullmove number to 1.
+///
+/// Operations are returned keyed by their opcode, with any surrounding quotes removed from the
+/// operand.
+#[allow(dead_code)]
+pub fn parse_epd(epd: &str) -> Result<(BoardState, HashMap<String, String>), String> {
+    let mut fields = epd.split_whitespace();
+    let position: Vec<&str> = fields.by_ref().take(4).collect();
+    if position.len() != 4 {
+        return Err("EPD does not have the four position fields, is invalid".to_string());
+    }
+    let board_state = parse_fen(&position.join(" "))?;
+
+    let mut operations = HashMap::new();
+    for operation in fields.collect::<Vec<&str>>().join(" ").split(';') {
+        let mut operation = operation.trim().splitn(2, ' ');
+        let opcode = match operation.next() {
+            Some(opcode) if !opcode.is_empty() => opcode,
+            _ => continue,
+        };
+        let operand = operation.next().unwrap_or("").trim().trim_matches('"');
+        operations.insert(opcode.to_string(), operand.to_string());
+    }
+
+    Ok((board_state, operations))
+}
+
+/// Return the FEN string which describes the given position.
+#[allow(dead_code)]
+pub fn to_fen(pos: &BoardState) -> String {
+    let mut ranks = Vec::with_capacity(8);
+    for rank in (0..8).rev() {
+        let mut contents = String::new();
+        let mut empty = 0;
+        for file in 0..8 {
+            let square = rank_file_to_index(rank, file);
+            match (pos.type_on(square), pos.color_on(square)) {
+                (Some(piece), Some(color)) => {
+                    if empty > 0 {
+                        contents.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    contents.push(match color {
+                        Color::White => piece.to_char().to_ascii_uppercase(),
+                        Color::Black => piece.to_char(),
+                    });
+                }
+                _ => empty += 1,
+            }
+        }
+        if empty > 0 {
+            contents.push_str(&empty.to_string());
+        }
+        ranks.push(contents);
+    }
+
+    let active_color = match pos.active_player {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+
+    let rights = pos.castling_rights;
+    let mut castling: String = [
+        (rights.white_king, 'K'),
+        (rights.white_queen, 'Q'),
+        (rights.black_king, 'k'),
+        (rights.black_queen, 'q'),
+    ]
+    .iter()
+    .filter(|(allowed, _)| *allowed)
+    .map(|(_, c)| *c)
+    .collect();
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    let en_passant = pos
+        .en_passant
+        .map_or_else(|| "-".to_string(), square_to_algebraic);
+
+    format!(
+        "{} {} {} {} {} {}",
+        ranks.join("/"),
+        active_color,
+        castling,
+        en_passant,
+        pos.half_move,
+        pos.full_move
+    )
+}
+
 fn parse_ranks(fen: &str) -> Result<Position, String> {
     let mut p = Position::empty();
     let s: Vec<&str> = fen.split('/').collect();
@@ -64,37 +165,35 @@ fn parse_active_color(fen: &str) -> Result<Color, String> {
 }
 
 fn parse_castling_rights(fen: &str) -> Castle {
-    let mut white_king = false;
-    let mut white_queen = false;
-    let mut black_king = false;
-    let mut black_queen = false;
+    let mut rights = Castle::none();
     for c in fen.chars() {
         match c {
-            'K' => white_king = true,
-            'Q' => white_queen = true,
-            'k' => black_king = true,
-            'q' => black_queen = true,
+            'K' => rights.white_king = true,
+            'Q' => rights.white_queen = true,
+            'k' => rights.black_king = true,
+            'q' => rights.black_queen = true,
             _ => (),
         }
     }
-    Castle {
-        white_king,
-        white_queen,
-        black_king,
-        black_queen,
-    }
+    rights
 }
 
-fn parse_en_passant(fen: &str) -> Option<Square> {
-    let c = fen.chars().next().unwrap();
-    match c {
-        '-' => None,
-        _ => Some(algebraic_to_square(&fen[0..2])),
+fn parse_en_passant(fen: &str) -> Result<Option<Square>, String> {
+    match fen {
+        "-" => Ok(None),
+        _ => square_from_algebraic(fen)
+            .map(Some)
+            .ok_or_else(|| "Cannot parse en passant square".to_string()),
     }
 }
 
-fn parse_move(fen: &str) -> u8 {
-    fen.parse().unwrap()
+/// Parse a move counter. Counters too large for the board are saturated, since only long games
+/// reach them.
+fn parse_move(fen: &str, name: &str) -> Result<u16, String> {
+    match fen.parse::<u64>() {
+        Ok(count) => Ok(u16::try_from(count).unwrap_or(u16::MAX)),
+        Err(_) => Err(format!("Cannot parse {name}")),
+    }
 }
 
 #[cfg(test)]
@@ -109,6 +208,31 @@ mod tests {
         assert_eq!(position.unwrap().bb_all(), 18_446_462_598_732_906_495);
     }
 
+    #[test]
+    fn parses_fen_without_move_counters() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3";
+        let position = parse_fen(fen).unwrap();
+        assert_eq!(position.active_player, Color::Black);
+        assert_eq!(position.en_passant, Some(20));
+        assert_eq!(position.half_move, 0);
+        assert_eq!(position.full_move, 1);
+
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq").is_err());
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -").is_err());
+    }
+
+    #[test]
+    fn parses_move_counters_of_long_games() {
+        let position = parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 300").unwrap();
+        assert_eq!(position.full_move, 300);
+        let position = parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 300 400").unwrap();
+        assert_eq!(position.half_move, u8::MAX);
+
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").is_err());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 x").is_err());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - -1 1").is_err());
+    }
+
     #[test]
     fn parses_random_board_1() {
         let fen = "5K1b/8/2P1q1P1/2p5/p2N2p1/7P/2QRPP2/k6B w - - 0 1";
@@ -165,6 +289,14 @@ mod tests {
         assert_eq!(position.active_player, Color::Black);
     }
 
+    #[test]
+    fn rejects_malformed_en_passant() {
+        for ep in ["e", "e9", "z3", "e3x"].iter() {
+            let fen = format!("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq {ep} 0 1");
+            assert!(parse_fen(&fen).is_err());
+        }
+    }
+
     #[test]
     fn parses_en_passant() {
         let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
@@ -180,6 +312,30 @@ mod tests {
         assert_eq!(position.full_move, 1);
     }
 
+    #[test]
+    fn writes_fen_which_parses_back() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 17",
+            "8/8/8/8/8/8/8/k6K b - - 99 80",
+        ];
+        for fen in fens.iter() {
+            assert_eq!(to_fen(&parse_fen(fen).unwrap()), *fen);
+        }
+    }
+
+    #[test]
+    fn parses_epd_operations() {
+        let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+        let (position, operations) = parse_epd(epd).unwrap();
+        assert_eq!(position.half_move, 0);
+        assert_eq!(position.full_move, 1);
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations["bm"], "Qg6");
+        assert_eq!(operations["id"], "WAC.001");
+    }
+
     #[test]
     #[should_panic]
     fn panics_on_incorrect_fen_ranks() {
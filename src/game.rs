@@ -1,10 +1,54 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
 use crate::board::BoardState;
-use crate::chess_move::{EvaledMove, Move};
-use crate::fen::parse_fen;
-use crate::move_gen::{debug_print, MoveGenerator};
+use crate::chess_move::{EvaledMove, Move, MoveType};
+use crate::clock::{Clock, TimeControl};
+use crate::fen::{parse_epd, parse_fen, to_fen};
+use crate::move_gen::{king_square, MoveGenerator, PerftStats};
+use crate::piece::{Color, Piece, PieceType};
 use crate::search::alpha_beta::AlphaBeta;
-use crate::search::search::Searcher;
+use crate::search::eval::{eval, Eval};
+use crate::search::search::{SearchResult, Searcher};
 use crate::search::stats::Stats;
+use crate::search::tablebase::Tablebase;
+use crate::square::square_to_algebraic;
+use crate::table::{ZobristTable, ZOBRIST_SEED};
+
+/// The number of half moves without a capture or pawn move after which either player may claim a
+/// draw.
+const FIFTY_MOVE_LIMIT: u8 = 100;
+
+/// The number of half moves without a capture or pawn move after which the game is drawn without
+/// either player having to claim it.
+const SEVENTY_FIVE_MOVE_LIMIT: u8 = 150;
+
+/// The depth searched to under a time control, which is deep enough that the search always runs
+/// out of time first.
+const CLOCK_SEARCH_DEPTH: usize = 64;
+
+/// The size in megabytes of the transposition table used to search for a hint.
+const HINT_TABLE_MB: usize = 1;
+
+/// The state of a game, as seen from the current position.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    /// The active player has at least one legal move and the game is not drawn, although a draw
+    /// may be claimable, see `Game::can_claim_draw`.
+    Ongoing,
+    /// The active player is in check and has no legal moves.
+    Checkmate,
+    /// The active player is not in check but has no legal moves.
+    Stalemate,
+    /// The current position has occurred for the third time.
+    DrawByRepetition,
+    /// Seventy-five moves have been played by each side without a capture or pawn move.
+    DrawBySeventyFiveMove,
+}
 
 /// A struct which encapsulates a chess game, which includes the ability to generate legal moves
 /// and determine the best move from a given position.
@@ -20,6 +64,11 @@ pub struct Game {
     gen: MoveGenerator,
     pos: BoardState,
     searcher: AlphaBeta,
+    start: BoardState,
+    history: Vec<Move>,
+    hashes: Vec<u64>,
+    clock: Option<Clock>,
+    zobrist: ZobristTable,
     // TODO: implement cached value for legal_moves
     // TODO: implement stack of previously chosen positions
 }
@@ -28,18 +77,76 @@ impl Game {
     /// Construct a new game from the default starting position.
     #[must_use]
     pub fn new() -> Game {
-        let gen = MoveGenerator::new();
-        let pos = BoardState::default();
-        let searcher = AlphaBeta::new();
-        Game { gen, pos, searcher }
+        Game::from_position(BoardState::default())
+    }
+
+    /// Construct a new game starting from the given position.
+    fn from_position(mut pos: BoardState) -> Game {
+        let zobrist = ZobristTable::from_seed(ZOBRIST_SEED);
+        let hashes = vec![zobrist.hash(&mut pos)];
+        Game {
+            gen: MoveGenerator::new(),
+            pos,
+            searcher: AlphaBeta::new(),
+            start: pos,
+            history: Vec::new(),
+            hashes,
+            clock: None,
+            zobrist,
+        }
     }
 
     /// Construct a new game using the given FEN string.
     pub fn from_fen(fen: &str) -> Result<Game, String> {
-        let gen = MoveGenerator::new();
-        let pos = parse_fen(fen)?;
-        let searcher = AlphaBeta::new();
-        Ok(Game { gen, pos, searcher })
+        Ok(Game::from_position(parse_fen(fen)?))
+    }
+
+    /// Construct a new game from a list of pieces and the squares they stand on, numbered from
+    /// 0 (`a1`) to 63 (`h8`), with the given player to move. Neither side may castle, and no en
+    /// passant capture is possible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless each side has exactly one king, or if a square is out of range or
+    /// used twice, or if a pawn stands on the first or eighth rank.
+    pub fn from_pieces(
+        placements: &[(u8, Color, PieceType)],
+        side_to_move: Color,
+    ) -> Result<Game, String> {
+        let mut pos = BoardState::empty();
+        pos.active_player = side_to_move;
+        pos.full_move = 1;
+        for &(square, color, piece) in placements {
+            if square >= 64 {
+                return Err(format!("Square {square} is not on the board"));
+            }
+            if pos.type_on(square).is_some() {
+                return Err(format!("Square {square} holds more than one piece"));
+            }
+            if piece == PieceType::Pawn && !(8..56).contains(&square) {
+                return Err(format!("Pawn on square {square} is on a back rank"));
+            }
+            pos.add(piece, color, square);
+        }
+        for color in Color::iterator() {
+            if pos.bb(*color, PieceType::King).count_ones() != 1 {
+                return Err(format!("{color:?} must have exactly one king"));
+            }
+        }
+
+        Ok(Game::from_position(pos))
+    }
+
+    /// Construct a new game from the given EPD line, returning it along with the EPD operations
+    /// keyed by opcode. For example, the best move of a test suite position can be found with
+    /// `game.move_from_san(&operations["bm"])`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the EPD does not start with a valid position.
+    pub fn from_epd(epd: &str) -> Result<(Game, HashMap<String, String>), String> {
+        let (pos, operations) = parse_epd(epd)?;
+        Ok((Game::from_position(pos), operations))
     }
 
     /// Using the current state of the game, return the move which is best
@@ -48,8 +155,40 @@ impl Game {
     /// `best_move` uses a searcher which implements a transposition table.
     /// Note that the table *is not* cleared between runs automatically and must
     /// be manually reset if you need to do so.
-    pub fn best_move(&mut self) -> EvaledMove {
-        self.searcher.best_move(&mut self.pos)
+    ///
+    /// If a time control has been set with `set_time_control`, the search is given a share of
+    /// the remaining time on the clock, and the time it takes is taken off the clock.
+    ///
+    /// Returns `None` if the active player is checkmated or stalemated, as there is no move to
+    /// make.
+    pub fn best_move(&mut self) -> Option<EvaledMove> {
+        if self.legal_moves().is_empty() {
+            return None;
+        }
+        let Some(clock) = self.clock.as_mut() else {
+            return Some(self.searcher.best_move(&mut self.pos));
+        };
+
+        let start = Instant::now();
+        self.searcher.move_time(clock.budget());
+        let mv = self
+            .searcher
+            .best_move_depth(&mut self.pos, CLOCK_SEARCH_DEPTH);
+        self.searcher.clear_move_time();
+        clock.spend(start.elapsed().as_millis());
+        Some(mv)
+    }
+
+    /// Play under the given time control from now on, starting with a full clock. Each call to
+    /// `best_move` then manages its own time, as an engine playing a match would.
+    pub fn set_time_control(&mut self, control: TimeControl) {
+        self.clock = Some(Clock::new(control));
+    }
+
+    /// Return the time left on the clock in milliseconds, or `None` if no time control is set.
+    #[must_use]
+    pub fn time_remaining(&self) -> Option<u128> {
+        self.clock.as_ref().map(Clock::remaining)
     }
 
     /// Using the current state of the game, return the move which is best
@@ -59,8 +198,68 @@ impl Game {
     /// `best_move_depth` uses a searcher which implements a transposition table.
     /// Note that the table *is not* cleared between runs automatically and must
     /// be manually reset if you need to do so.
-    pub fn best_move_depth(&mut self, depth: usize) -> EvaledMove {
-        self.searcher.best_move_depth(&mut self.pos, depth)
+    ///
+    /// Returns `None` if the active player is checkmated or stalemated, as there is no move to
+    /// make.
+    pub fn best_move_depth(&mut self, depth: usize) -> Option<EvaledMove> {
+        if self.legal_moves().is_empty() {
+            return None;
+        }
+        Some(self.searcher.best_move_depth(&mut self.pos, depth))
+    }
+
+    /// Search the current position to the given depth like `best_move_depth`, returning the
+    /// depth reached, the number of nodes searched, and the principal variation along with the
+    /// best move and its score.
+    ///
+    /// Returns `None` if the active player is checkmated or stalemated, as there is no move to
+    /// make.
+    pub fn search(&mut self, depth: usize) -> Option<SearchResult> {
+        if self.legal_moves().is_empty() {
+            return None;
+        }
+        Some(self.searcher.search(&mut self.pos, depth))
+    }
+
+    /// Search the current position to the given depth and return the best move as a hint,
+    /// without affecting the game. The search uses the evaluation and tablebase set on the game,
+    /// but its own small transposition table, so the game's table is not warmed and its stats are
+    /// left as they were. Returns `None` if the active player is checkmated or stalemated.
+    #[must_use]
+    pub fn hint_move(&self, depth: usize) -> Option<Move> {
+        if self.legal_moves().is_empty() {
+            return None;
+        }
+        let mut pos = self.pos;
+        let mut searcher = self.searcher.fork_with_table_mb(HINT_TABLE_MB);
+        Some(searcher.best_move_depth(&mut pos, depth).mv)
+    }
+
+    /// Return a static evaluation of the current position in centipawns from white's perspective,
+    /// so positive scores favour white regardless of which player is to move. No search is
+    /// performed.
+    ///
+    /// Note that the evaluation includes a small bonus for the player to move, so outside of pawn
+    /// endgames the same placement of pieces scores slightly differently depending on whose turn
+    /// it is.
+    #[must_use]
+    pub fn evaluation_white(&self) -> isize {
+        match self.pos.active_player {
+            Color::White => eval(&self.pos),
+            Color::Black => -eval(&self.pos),
+        }
+    }
+
+    /// Search with the given static evaluation instead of the built-in one from now on, both in
+    /// `search` and `hint_move`. `evaluation_white` and `legal_moves_evaluated` keep using the
+    /// built-in evaluation.
+    pub fn set_eval(&mut self, evaluator: Box<dyn Eval>) {
+        self.searcher.set_eval(evaluator);
+    }
+
+    /// Consult the given endgame tablebase in `search` and `hint_move` from now on.
+    pub fn set_tablebase(&mut self, tablebase: Box<dyn Tablebase>) {
+        self.searcher.set_tablebase(tablebase);
     }
 
     /// Return a vector of all legal moves from the current position.
@@ -69,33 +268,498 @@ impl Game {
         self.gen.all_moves(&self.pos)
     }
 
+    /// Return a vector of all legal moves of the piece on the given square, which is empty if the
+    /// square holds no piece of the active player. Squares are numbered from 0 (`a1`) to 63 (`h8`).
+    #[must_use]
+    pub fn legal_moves_from(&self, from: u8) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == from)
+            .collect()
+    }
+
+    /// Return a bitboard of every square the piece on the given square can legally move to, using
+    /// the same square numbering as `attack_map`. Promotions to different pieces share a single
+    /// destination square.
+    #[must_use]
+    pub fn legal_destinations(&self, from: u8) -> u64 {
+        self.legal_moves_from(from)
+            .iter()
+            .fold(0, |destinations, mv| destinations | (1 << mv.to))
+    }
+
+    /// Return whether the given move is legal in the current position, such as a move returned by
+    /// `move_from_san` or `legal_moves` in another position. Only the pseudo-legal moves are
+    /// generated, and the move is then checked for leaving the king in check, which is cheaper
+    /// than searching `legal_moves` for it.
+    #[must_use]
+    pub fn is_legal(&self, mv: Move) -> bool {
+        if self.pos.bb(self.pos.active_player, PieceType::King) == 0 {
+            return false;
+        }
+        if !self.gen.pseudo_legal_moves(&self.pos).contains(&mv) {
+            return false;
+        }
+        let king_square = king_square(&self.pos);
+        let blockers = self.gen.calculate_blockers(&self.pos, king_square);
+        let checkers = self.gen.attacks_to(&self.pos, king_square);
+        self.gen
+            .is_legal(&self.pos, &mv, blockers, checkers, king_square)
+    }
+
+    /// Return a vector of all legal moves from the current position, each annotated with a static
+    /// evaluation of the resulting position from the perspective of the active player.
+    /// No search is performed, so this is much cheaper (and less accurate) than `best_move`.
+    /// The returned moves are sorted from best to worst.
+    #[must_use]
+    pub fn legal_moves_evaluated(&self) -> Vec<EvaledMove> {
+        let mut moves: Vec<EvaledMove> = self
+            .legal_moves()
+            .into_iter()
+            .map(|mv| EvaledMove {
+                mv,
+                eval: -eval(&self.pos.clone_with_move(mv)),
+            })
+            .collect();
+        moves.sort_by(|a, b| b.cmp(a));
+        moves
+    }
+
+    /// Return the net material, in centipawns, that the active player wins by playing the given
+    /// move and letting both sides recapture on its destination square while it is profitable.
+    /// A negative value means the move loses material, such as a rook taking a defended knight.
+    #[must_use]
+    pub fn see(&self, mv: Move) -> isize {
+        self.gen.see(&self.pos, mv)
+    }
+
+    /// Return a bitboard of every square attacked by the given color, with square `a1` as the
+    /// least significant bit and `h8` as the most significant. Unlike the destinations of legal
+    /// moves, this includes squares occupied by the attacker's own pieces and squares behind the
+    /// opposing king.
+    #[must_use]
+    pub fn attack_map(&self, color: Color) -> u64 {
+        self.gen.attack_map(&self.pos, color)
+    }
+
+    /// Return the number of opposing pieces, other than the king, which attack the king of the
+    /// given color or any of the squares next to it. The more pieces join an attack on the king,
+    /// the higher the count, so it gives a simple measure of the danger the king is in.
+    #[must_use]
+    pub fn king_attackers(&self, color: Color) -> u32 {
+        self.gen.king_attackers(&self.pos, color)
+    }
+
+    /// Return a bitboard of the active player's pieces which are pinned to their king, using the
+    /// same square numbering as `attack_map`. A pinned piece may still move along the pin.
+    #[must_use]
+    pub fn pinned_pieces(&self) -> u64 {
+        self.gen.pinned_pieces(&self.pos)
+    }
+
+    /// Return a bitboard of the pieces giving check to the active player, using the same square
+    /// numbering as `attack_map`. Two bits are set in case of a double check.
+    #[must_use]
+    pub fn checkers(&self) -> u64 {
+        self.gen.checkers(&self.pos)
+    }
+
+    /// Return whether or not the given move puts the opponent in check, without playing it. The
+    /// move must be legal in the current position.
+    #[must_use]
+    pub fn in_check_after(&self, mv: Move) -> bool {
+        let new_pos = self.pos.clone_with_move(mv);
+        self.gen.is_in_check(&new_pos)
+    }
+
+    /// Return the status of the game in the current position. Only draws which need no claim are
+    /// reported, so a game in which a draw could be claimed is still ongoing, see
+    /// `can_claim_draw`. Checkmate and stalemate take precedence over the seventy-five move rule,
+    /// so a move which delivers mate on the hundred and fiftieth half move wins the game.
+    #[must_use]
+    pub fn status(&self) -> GameStatus {
+        if self.legal_moves().is_empty() {
+            if self.gen.is_in_check(&self.pos) {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.pos.half_move >= SEVENTY_FIVE_MOVE_LIMIT {
+            GameStatus::DrawBySeventyFiveMove
+        } else if self.repetitions() >= 3 {
+            GameStatus::DrawByRepetition
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// Return whether or not the active player has been checkmated.
+    #[must_use]
+    pub fn in_checkmate(&self) -> bool {
+        self.status() == GameStatus::Checkmate
+    }
+
+    /// Return whether or not the active player has been stalemated, i.e. they have no legal moves
+    /// but are not in check.
+    #[must_use]
+    pub fn in_stalemate(&self) -> bool {
+        self.status() == GameStatus::Stalemate
+    }
+
+    /// Return whether the game is drawn without either player having to claim it: by stalemate,
+    /// by the same position occurring for the third time, or by seventy-five moves by each side
+    /// without a capture or pawn move, unless the last move delivered mate.
+    #[must_use]
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self.status(),
+            GameStatus::Stalemate
+                | GameStatus::DrawByRepetition
+                | GameStatus::DrawBySeventyFiveMove
+        )
+    }
+
+    /// Return whether the active player may claim a draw, because the current position has
+    /// already occurred earlier in the game or because fifty moves have been played by each side
+    /// without a capture or pawn move. Any game which `is_draw` may be claimed as well.
+    #[must_use]
+    pub fn can_claim_draw(&self) -> bool {
+        match self.status() {
+            GameStatus::Checkmate => false,
+            GameStatus::Ongoing => {
+                self.repetitions() >= 2 || self.pos.half_move >= FIFTY_MOVE_LIMIT
+            }
+            _ => true,
+        }
+    }
+
+    /// Return the hash of every position of the game, from the starting position up to and
+    /// including the current one. Two positions have the same hash when the same pieces stand on
+    /// the same squares with the same player to move, castling rights and en passant square, so
+    /// repeated positions can be found by comparing hashes. The hashes do not change between runs.
+    #[must_use]
+    pub fn zobrist_history(&self) -> Vec<u64> {
+        self.hashes.clone()
+    }
+
+    /// Return the number of times the current position has occurred in the game, including now.
+    /// Positions from before the last capture or pawn move can never occur again, and neither
+    /// can those with the other player to move, so only every other reversible position is
+    /// compared.
+    fn repetitions(&self) -> usize {
+        let current = self.hashes[self.hashes.len() - 1];
+        let reversible = usize::from(self.pos.half_move).min(self.hashes.len() - 1);
+        self.hashes
+            .iter()
+            .rev()
+            .take(reversible + 1)
+            .step_by(2)
+            .filter(|hash| **hash == current)
+            .count()
+    }
+
+    /// Return the legal move described by the given move in standard algebraic notation (e.g.
+    /// `Nf3`, `exd5`, `Rad1`, `e8=Q+` or `O-O`), or `None` if no single legal move matches.
+    #[must_use]
+    pub fn move_from_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(&['+', '#', '!', '?'][..]);
+        let castle = match san {
+            "O-O" | "0-0" => Some(MoveType::CastleKing),
+            "O-O-O" | "0-0-0" => Some(MoveType::CastleQueen),
+            _ => None,
+        };
+        if let Some(kind) = castle {
+            return self.legal_moves().into_iter().find(|mv| mv.kind == kind);
+        }
+
+        let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x' && *c != '=').collect();
+        let promotion = match chars.last() {
+            Some(c) if "QRBN".contains(*c) && chars.len() > 2 => {
+                Some(Piece::convert_char_to_piece(chars.pop()?))
+            }
+            _ => None,
+        };
+        let piece = match chars.first() {
+            Some(c) if "KQRBN".contains(*c) => Piece::convert_char_to_piece(chars.remove(0)),
+            _ => PieceType::Pawn,
+        };
+        if chars.len() < 2 {
+            return None;
+        }
+        let to: String = chars.split_off(chars.len() - 2).into_iter().collect();
+
+        let mut candidates = self.legal_moves().into_iter().filter(|mv| {
+            let algebraic = mv.to_algebraic();
+            !mv.is_castle()
+                && self.pos.type_on(mv.from) == Some(piece)
+                && algebraic[2..4] == to
+                && mv.promoted_piece() == promotion
+                && chars.iter().all(|c| algebraic[0..2].contains(*c))
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Some(mv),
+            _ => None,
+        }
+    }
+
     /// Apply the given move to the game, returns an error if the given move is illegal.
     pub fn make_move(&mut self, mv: Move) -> Result<(), &'static str> {
         let legal_moves = self.legal_moves();
         if !legal_moves.contains(&mv) {
             return Err("Move is not legal in the position");
         }
-        self.pos.make_move(mv);
+        self.play(mv);
+        Ok(())
+    }
+
+    /// Apply the given moves in long algebraic notation, such as `e2e4` or `e7e8q`, in order.
+    ///
+    /// # Errors
+    ///
+    /// Stops at the first illegal move and returns an error naming its index; the moves before it
+    /// remain applied.
+    pub fn make_moves(&mut self, moves: &[&str]) -> Result<(), String> {
+        for (i, algebraic) in moves.iter().enumerate() {
+            let mv = self
+                .legal_moves()
+                .into_iter()
+                .find(|mv| mv.to_algebraic() == *algebraic)
+                .ok_or_else(|| format!("Move {i} ({algebraic}) is not legal in the position"))?;
+            self.play(mv);
+        }
         Ok(())
     }
 
+    /// Play the given legal move, recording it and the hash of the resulting position.
+    fn play(&mut self, mv: Move) {
+        self.pos.make_move(mv);
+        self.history.push(mv);
+        self.hashes.push(self.zobrist.hash(&mut self.pos));
+    }
+
+    /// Reset the game to the standard starting position. The searcher is kept, including its
+    /// transposition table, so this is much cheaper than constructing a new `Game`.
+    pub fn reset_to_startpos(&mut self) {
+        self.pos = BoardState::default();
+        self.start = self.pos;
+        self.history.clear();
+        self.hashes = vec![self.zobrist.hash(&mut self.pos)];
+    }
+
+    /// Return the moves which have been played in this game, in the order they were played.
+    #[must_use]
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Return the en passant target square in algebraic notation, such as `e3`. As in FEN, it is
+    /// set after every double pawn push, whether or not an opposing pawn is able to capture.
+    #[must_use]
+    pub fn en_passant_square(&self) -> Option<String> {
+        self.pos.en_passant.map(square_to_algebraic)
+    }
+
+    /// Return the FEN string of the current position. The en passant square is included after
+    /// every double pawn push, see `en_passant_square`.
+    #[must_use]
+    pub fn fen(&self) -> String {
+        to_fen(&self.pos)
+    }
+
+    /// Return the FEN string of the current position, only including the en passant square when
+    /// an en passant capture is legal, as some GUIs and position databases expect.
+    #[must_use]
+    pub fn fen_legal_en_passant(&self) -> String {
+        let mut pos = self.pos;
+        if !self.legal_moves().iter().any(Move::is_en_passant_capture) {
+            pos.en_passant = None;
+        }
+        to_fen(&pos)
+    }
+
+    /// Play a move chosen uniformly at random from the legal moves in the current position, and
+    /// return it. Returns `None`, leaving the game unchanged, if there are no legal moves.
+    ///
+    /// Passing a seeded `rng` makes the sequence of moves reproducible.
+    pub fn random_move(&mut self, rng: &mut impl Rng) -> Option<Move> {
+        let mv = *self.legal_moves().choose(rng)?;
+        self.play(mv);
+        Some(mv)
+    }
+
+    /// Return the game in PGN format, with the movetext written in standard algebraic notation.
+    /// The given tags are written in order; if none are given, the Seven Tag Roster is written
+    /// with unknown values. Games which did not start from the standard starting position also
+    /// get `SetUp` and `FEN` tags so that the movetext can be replayed.
+    #[must_use]
+    pub fn to_pgn(&self, tags: &[(String, String)]) -> String {
+        let result = match self.status() {
+            GameStatus::Checkmate => match self.pos.active_player {
+                Color::White => "0-1",
+                Color::Black => "1-0",
+            },
+            GameStatus::Stalemate
+            | GameStatus::DrawByRepetition
+            | GameStatus::DrawBySeventyFiveMove => "1/2-1/2",
+            GameStatus::Ongoing => "*",
+        };
+
+        let mut pgn = String::new();
+        if tags.is_empty() {
+            let roster = [
+                ("Event", "?"),
+                ("Site", "?"),
+                ("Date", "????.??.??"),
+                ("Round", "?"),
+                ("White", "?"),
+                ("Black", "?"),
+                ("Result", result),
+            ];
+            for (name, value) in &roster {
+                writeln!(pgn, "[{name} \"{value}\"]").unwrap();
+            }
+        } else {
+            for (name, value) in tags {
+                writeln!(pgn, "[{name} \"{value}\"]").unwrap();
+            }
+        }
+        let fen = to_fen(&self.start);
+        if fen != to_fen(&BoardState::default()) {
+            writeln!(pgn, "[SetUp \"1\"]\n[FEN \"{fen}\"]").unwrap();
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::with_capacity(self.history.len() * 3 / 2 + 1);
+        let mut pos = self.start;
+        for (i, mv) in self.history.iter().enumerate() {
+            match pos.active_player {
+                Color::White => tokens.push(format!("{}.", pos.full_move)),
+                Color::Black if i == 0 => tokens.push(format!("{}...", pos.full_move)),
+                Color::Black => {}
+            }
+            tokens.push(self.san_in(&pos, *mv));
+            pos.make_move(*mv);
+        }
+        tokens.push(result.to_string());
+
+        // Keep lines of movetext below 80 characters, as recommended by the PGN standard.
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + token.len() + 1 > 79 {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        pgn
+    }
+
+    /// Return the given legal move in standard algebraic notation, as played from the current
+    /// position, such as `Nbd2`, `exd6`, or `Qh7#`. The moving piece is disambiguated by file,
+    /// rank, or both only when another piece of the same kind could reach the same square.
+    #[must_use]
+    pub fn san(&self, mv: Move) -> String {
+        self.san_in(&self.pos, mv)
+    }
+
+    /// Return the given legal move in standard algebraic notation, as played from the given
+    /// position.
+    fn san_in(&self, pos: &BoardState, mv: Move) -> String {
+        let mut san = match mv.kind {
+            MoveType::CastleKing => "O-O".to_string(),
+            MoveType::CastleQueen => "O-O-O".to_string(),
+            _ => {
+                let algebraic = mv.to_algebraic();
+                let (from, to) = (&algebraic[0..2], &algebraic[2..4]);
+                let piece = pos.type_on(mv.from).unwrap_or(PieceType::Pawn);
+                let mut san = String::new();
+                if piece == PieceType::Pawn {
+                    if mv.is_capture() {
+                        san.push_str(&from[0..1]);
+                    }
+                } else {
+                    san.push(piece.to_char().to_ascii_uppercase());
+                    let rivals: Vec<String> = self
+                        .gen
+                        .all_moves(pos)
+                        .into_iter()
+                        .filter(|other| {
+                            other.to == mv.to
+                                && other.from != mv.from
+                                && pos.type_on(other.from) == Some(piece)
+                        })
+                        .map(Move::to_algebraic)
+                        .collect();
+                    if !rivals.is_empty() {
+                        if rivals.iter().all(|other| other[0..1] != from[0..1]) {
+                            san.push_str(&from[0..1]);
+                        } else if rivals.iter().all(|other| other[1..2] != from[1..2]) {
+                            san.push_str(&from[1..2]);
+                        } else {
+                            san.push_str(from);
+                        }
+                    }
+                }
+                if mv.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(to);
+                if let Some(promoted) = mv.promoted_piece() {
+                    san.push('=');
+                    san.push(promoted.to_char().to_ascii_uppercase());
+                }
+                san
+            }
+        };
+
+        let next = pos.clone_with_move(mv);
+        if self.gen.is_in_check(&next) {
+            san.push(if self.gen.all_moves(&next).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
     /// Runs a performance test of the Game's move generator, returning the total number
-    /// of nodes calculated at the given depth.
+    /// of nodes calculated at the given depth. Comparing the result against published node
+    /// counts is a quick way to validate move generation.
+    ///
+    /// ```rust
+    /// use purple::Game;
+    ///
+    /// assert_eq!(Game::new().perft(3), 8902);
+    /// ```
     #[must_use]
     pub fn perft(&self, depth: usize) -> usize {
         self.gen.perft(&self.pos, depth)
     }
 
+    /// Runs a performance test of the Game's move generator, returning the total number of nodes
+    /// at the given depth along with a breakdown of captures, en passants, castles, promotions,
+    /// and checks among them.
+    #[must_use]
+    pub fn perft_with_stats(&self, depth: usize) -> PerftStats {
+        self.gen.perft_with_stats(&self.pos, depth)
+    }
+
     /// Set whether or not the move searcher should use a transposition table to remember
     /// previously seen positions and their evaluations.
     pub fn use_table(&mut self, setting: bool) {
         self.searcher.use_table(setting);
     }
 
-    /// Return a string representing the position, useful for debugging purposes.
+    /// Return a string representing the position as an ASCII board, useful for debugging purposes.
     #[must_use]
     pub fn debug(&self) -> String {
-        debug_print(&self.pos)
+        self.pos.to_string()
     }
 
     /// Return a stats struct, which contains metrics for the previous search
@@ -104,3 +768,39 @@ impl Game {
         self.searcher.stats()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn play_random_game(seed: u64, plies: usize) -> Vec<Move> {
+        let mut game = Game::new();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut moves = Vec::new();
+        for _ in 0..plies {
+            match game.random_move(&mut rng) {
+                Some(mv) => moves.push(mv),
+                None => break,
+            }
+
+            game.pos.assert_invariants();
+            // The player who just moved must not have left their own king in check.
+            let mut previous = game.pos;
+            previous.switch();
+            assert!(!game.gen.is_in_check(&previous));
+        }
+        moves
+    }
+
+    #[test]
+    fn random_moves_are_reproducible() {
+        for seed in 0..10 {
+            let moves = play_random_game(seed, 200);
+            assert!(!moves.is_empty());
+            assert_eq!(moves, play_random_game(seed, 200));
+        }
+    }
+}
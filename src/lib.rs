@@ -5,6 +5,8 @@
 //! Purple is a high-level API, meaning that many of the finer details are hidden from clients.
 //! For most functionality, clients will interact with the `Game` struct which manages
 //! move generator, move evaluation and selection, and board state.
+//! Clients who need more control over searching, such as setting a move time or reading the
+//! number of nodes searched, can drive a searcher directly through the `search` module.
 //!
 //! Purple also includes a UCI (Universal Chess Interface) module for use with UCI programs
 //! like `ArenaChess` and `CuteChess`.
@@ -18,22 +20,37 @@
 //! let mut game = Game::new(); // from the standard starting position
 //! let moves = game.legal_moves();
 //!
-//! let best_move = game.best_move();
-//! game.make_move(best_move.mv);
+//! if let Some(best_move) = game.best_move() {
+//!     game.make_move(best_move.mv);
+//! }
 //! ```
 //!
 
-pub use crate::game::Game;
+pub use crate::clock::TimeControl;
+pub use crate::game::{Game, GameStatus};
+pub use crate::piece::{Color, PieceType};
 
 mod bitboard;
 mod board;
 mod chess_move;
+mod clock;
 mod fen;
 mod game;
 mod magic;
 mod move_gen;
 mod piece;
-mod search;
+pub mod search;
 mod square;
 mod table;
 pub mod uci;
+
+/// Internals which are only exposed so that they can be measured by the benchmarks in `benches/`.
+/// Nothing in this module is considered part of the public API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::board::BoardState;
+    pub use crate::fen::parse_fen;
+    pub use crate::move_gen::MoveGenerator;
+    pub use crate::search::eval::eval;
+    pub use crate::table::ZobristTable;
+}
@@ -2,8 +2,8 @@ mod constants;
 
 use std::slice::Iter;
 
-use rand::prelude::ThreadRng;
-use rand::RngCore;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 
 use self::constants::{BISHOP_RELEVANT_BITS, ROOK_RELEVANT_BITS};
 use crate::bitboard::{
@@ -206,7 +206,7 @@ pub enum GenerationScheme {
 }
 
 pub struct MagicRandomizer {
-    random: ThreadRng,
+    random: StdRng,
     scheme: GenerationScheme,
     itr: Box<Iter<'static, u64>>,
 }
@@ -234,12 +234,23 @@ impl MagicRandomizer {
             GenerationScheme::PreComputed => Box::new(MAGIC_NUMBERS.iter()),
         };
         MagicRandomizer {
-            random: ThreadRng::default(),
+            random: StdRng::from_entropy(),
             scheme,
             itr,
         }
     }
 
+    /// Construct a randomizer which generates magic numbers at runtime from the given seed,
+    /// rather than using the precomputed magic numbers.
+    #[allow(dead_code)]
+    pub fn from_seed(seed: u64) -> MagicRandomizer {
+        MagicRandomizer {
+            random: StdRng::seed_from_u64(seed),
+            scheme: GenerationScheme::PseudoRandom,
+            itr: Box::new([0; 0].iter()),
+        }
+    }
+
     fn gen_u64(&mut self) -> u64 {
         let u1: u64 = self.random.next_u64() & 0xFFFF;
         let u2: u64 = self.random.next_u64() & 0xFFFF;
@@ -488,3 +499,33 @@ pub fn occupancy(occupancy_index: usize, bits: usize, mut attack_mask: Bitboard)
 
     b
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::square::SquareIndex::{A1, D4, E5, H8};
+
+    #[test]
+    fn generated_rook_table_matches_precomputed() {
+        let precomputed = MagicTable::init(
+            MagicPiece::Rook,
+            &mut MagicRandomizer::new(GenerationScheme::PreComputed),
+        );
+        let generated = MagicTable::init(MagicPiece::Rook, &mut MagicRandomizer::from_seed(7));
+
+        let blocker_sets: [Bitboard; 4] = [0, RANK4 | FILEE, 0x0000_1020_0840_0200, !0];
+        let squares: [Square; 4] = [A1 as Square, D4 as Square, E5 as Square, H8 as Square];
+        for square in squares.iter().copied() {
+            for blockers in blocker_sets.iter() {
+                assert_eq!(
+                    generated.moves(square, *blockers),
+                    precomputed.moves(square, *blockers)
+                );
+                assert_eq!(
+                    generated.moves(square, *blockers),
+                    rook_attacks(square, *blockers)
+                );
+            }
+        }
+    }
+}
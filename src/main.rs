@@ -4,15 +4,62 @@ use clap::{App, Arg};
 use itertools::Itertools;
 use purple::{self, Game};
 
+use crate::fen::parse_fen;
+use crate::piece::Color;
+use crate::search::alpha_beta::AlphaBeta;
+use crate::search::eval::{eval, eval_breakdown, SCALE_NORMAL};
+use crate::search::search::Searcher;
+use crate::search::{compare_searchers, Disagreement};
 use crate::uci::uci_loop;
 
+/// Positions with known perft node counts used by `--selftest`, given as (fen, depth, nodes).
+/// Depths are kept small enough for the whole suite to finish in a few seconds.
+const PERFT_SUITE: [(&str, usize, usize); 7] = [
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        4,
+        197_281,
+    ),
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        3,
+        97862,
+    ),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674_624),
+    (
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        3,
+        9467,
+    ),
+    (
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        3,
+        62379,
+    ),
+    (
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        3,
+        89890,
+    ),
+    (
+        "r6r/1bp2pP1/R2qkn2/1P6/1pPQ4/1B3N2/1B1P2p1/4K2R b K c3 0 1",
+        3,
+        109_269,
+    ),
+];
+
 mod bitboard;
 mod board;
 mod chess_move;
+// The binary only uses `Clock` to budget the time of a UCI `go` command.
+#[allow(dead_code)]
+mod clock;
 mod fen;
 mod magic;
 mod move_gen;
 mod piece;
+// The public re-exports of `search` are only used through the library.
+#[allow(unused_imports)]
 mod search;
 mod square;
 mod table;
@@ -49,8 +96,60 @@ fn main() {
                 .value_names(&["depth", "fen"])
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("compare-search")
+                .long("compare-search")
+                .help(
+                    "search every FEN in a file with two searcher configurations and report \
+                     where their best moves differ; a configuration is `default` or a comma \
+                     separated list of no-table, no-nmp, no-lmr, no-fp, no-aspiration, \
+                     no-root-ordering and no-q-checks",
+                )
+                .number_of_values(4)
+                .value_names(&["depth", "file", "first", "second"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("eval")
+                .long("eval")
+                .help("print the static evaluation of a position term by term, from white's view")
+                .value_name("fen")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("selftest")
+                .long("selftest")
+                .help("verify the move generator against a suite of known perft results"),
+        )
         .get_matches();
 
+    if matches.is_present("selftest") {
+        if !execute_selftest() {
+            std::process::exit(1);
+        }
+        return;
+    };
+
+    if matches.is_present("compare-search") {
+        let args = matches.values_of("compare-search").unwrap().collect_vec();
+        if let Err(e) = execute_compare_search(&args) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    };
+
+    if let Some(fen) = matches.value_of("eval") {
+        match eval_report(fen) {
+            Ok(lines) => lines.iter().for_each(|line| println!("{line}")),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    };
+
     if matches.is_present("perft") {
         execute_perft(matches.values_of("perft").unwrap().collect_vec());
         return;
@@ -79,12 +178,112 @@ fn execute_perft(args: Vec<&str>) {
     println!("Nodes: {}", nodes);
 }
 
+/// Run every position in the perft suite, printing the result of each. Returns whether or not
+/// all of them produced the expected number of nodes.
+fn execute_selftest() -> bool {
+    let mut passed = true;
+    for (fen, depth, expected) in PERFT_SUITE.iter() {
+        let game = Game::from_fen(fen).unwrap();
+        let nodes = game.perft(*depth);
+        if nodes == *expected {
+            println!("PASS depth {} {}", depth, fen);
+        } else {
+            println!(
+                "FAIL depth {} {} (expected {}, got {})",
+                depth, fen, expected, nodes
+            );
+            passed = false;
+        }
+    }
+    passed
+}
+
+/// Search every FEN in a file, one per line, with two searcher configurations and print the
+/// positions where they chose different best moves.
+fn execute_compare_search(args: &[&str]) -> Result<(), String> {
+    let depth = args[0]
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid depth {}", args[0]))?;
+    let contents = std::fs::read_to_string(args[1]).map_err(|e| format!("{}: {e}", args[1]))?;
+    let fens = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect_vec();
+
+    let mut first = configured_searcher(args[2])?;
+    let mut second = configured_searcher(args[3])?;
+    let disagreements = compare_searchers(&mut first, &mut second, &fens, depth)?;
+    for Disagreement { fen, first, second } in &disagreements {
+        println!(
+            "{fen}: {} ({}) vs {} ({})",
+            first.mv.to_algebraic(),
+            first.eval,
+            second.mv.to_algebraic(),
+            second.eval
+        );
+    }
+    println!(
+        "{} of {} positions disagree",
+        disagreements.len(),
+        fens.len()
+    );
+    Ok(())
+}
+
+/// Build an alpha-beta searcher from a configuration given on the command line, which is either
+/// `default` or a comma separated list of features to turn off.
+fn configured_searcher(config: &str) -> Result<AlphaBeta, String> {
+    let mut searcher = AlphaBeta::new();
+    if config == "default" {
+        return Ok(searcher);
+    }
+
+    for option in config.split(',') {
+        match option {
+            "no-table" => searcher.use_table(false),
+            "no-nmp" => searcher.use_null_move_pruning(false),
+            "no-lmr" => searcher.use_late_move_reductions(false),
+            "no-fp" => searcher.use_futility_pruning(false),
+            "no-aspiration" => searcher.clear_aspiration_window(),
+            "no-root-ordering" => searcher.root_move_ordering(false),
+            "no-q-checks" => searcher.quiescence_checks(0),
+            _ => return Err(format!("Unknown searcher option {option}")),
+        }
+    }
+    Ok(searcher)
+}
+
+/// Describe the static evaluation of a position, one term per line followed by the endgame scale
+/// factor and the total. Every score is given from white's perspective.
+fn eval_report(fen: &str) -> Result<Vec<String>, String> {
+    let pos = parse_fen(fen)?;
+    let sign = match pos.active_player {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+
+    let mut lines = Vec::new();
+    for (term, score) in eval_breakdown(&pos) {
+        if term == "scale" {
+            lines.push(format!("{term:<12} {score}/{SCALE_NORMAL}"));
+        } else {
+            lines.push(format!("{term:<12} {}", sign * score));
+        }
+    }
+    lines.push(format!("{:<12} {}", "total", sign * eval(&pos)));
+    Ok(lines)
+}
+
 fn execute_mini_perft(args: Vec<&str>) {
     let depth = args.first().unwrap().parse::<usize>().unwrap();
     let fen = args.get(1).unwrap();
 
     let mut game = Game::from_fen(fen).unwrap();
-    let mv = game.best_move_depth(depth);
+    let Some(mv) = game.best_move_depth(depth) else {
+        println!("No legal moves, the game is over");
+        return;
+    };
 
     let stats = game.stats();
     println!("Explored {} nodes", stats.nodes);
@@ -97,10 +296,48 @@ fn execute_alpha_perft(args: Vec<&str>) {
     let fen = args.get(1).unwrap();
 
     let mut game = Game::from_fen(fen).unwrap();
-    let mv = game.best_move_depth(depth);
+    let Some(mv) = game.best_move_depth(depth) else {
+        println!("No legal moves, the game is over");
+        return;
+    };
 
     let stats = game.stats();
     println!("Explored {} nodes", stats.nodes);
     println!("Best Move {}", mv.mv.to_algebraic());
     println!("Move Evaluation {}", mv.eval);
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selftest_passes() {
+        assert!(execute_selftest());
+    }
+
+    #[test]
+    fn evaluates_starting_position() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let lines = eval_report(fen).unwrap();
+        // Only the bonus for having the move separates the two sides.
+        for line in &lines {
+            let score = line.split_whitespace().last().unwrap();
+            if line.starts_with("tempo") || line.starts_with("total") {
+                assert_eq!(score, "10");
+            } else if line.starts_with("scale") {
+                assert_eq!(score, "16/16");
+            } else {
+                assert_eq!(score, "0", "{}", line);
+            }
+        }
+        assert!(eval_report("not a fen").is_err());
+    }
+
+    #[test]
+    fn configures_searchers_from_option_lists() {
+        assert!(configured_searcher("default").is_ok());
+        assert!(configured_searcher("no-nmp,no-lmr,no-fp").is_ok());
+        assert!(configured_searcher("no-nmp,fast").is_err());
+    }
+}
@@ -1,19 +1,28 @@
 use itertools::Itertools;
 
-use crate::bitboard::{
-    AddPiece, Bitboard, New, PieceItr, Shift, FILEA, FILEB, FILEG, FILEH, RANK2, RANK3, RANK6,
-    RANK7,
-};
+use crate::bitboard::{AddPiece, Bitboard, Direction, New, PieceItr, Shift};
 use crate::board::BoardState;
 use crate::chess_move::MoveType::{Capture, EnPassantCapture, Quiet};
-use crate::chess_move::{Move, MoveType, PromotionType, EAST, NORTH, SOUTH, WEST};
+use crate::chess_move::{CastleSide, Move, MoveType, PromotionType, EAST, WEST};
 use crate::magic::{GenerationScheme, MagicPiece, MagicRandomizer, MagicTable};
 use crate::piece::{Color, PieceType};
-use crate::square::SquareIndex::{C1, C8, E1, E8, G1, G8};
-use crate::square::{rank_file_to_index, Square};
+use crate::square::Square;
 
+/// The capacity reserved for a list of moves. No legal position has more than 218 legal moves, so
+/// this is never exceeded from a legal game, although the list would simply grow for a position
+/// which could not arise in one (such as one with a dozen queens).
 const MAX_MOVES: usize = 256;
 
+/// Pieces ordered from the least to the most valuable, the order in which they join an exchange.
+const EXCHANGE_ORDER: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
 pub struct Lookup {
     rook_table: MagicTable,
     bishop_table: MagicTable,
@@ -89,12 +98,10 @@ impl Lookup {
         let mut v: Vec<Bitboard> = Vec::with_capacity(64);
 
         for i in 0..64 {
-            let mut b: Bitboard = 0;
-            let mut r: Bitboard = 0;
-            b = b.add_at_square(i);
-            for dir in MoveType::king_itr() {
-                r |= b.shift(*dir);
-            }
+            let b = Bitboard::for_square(i);
+            let r = Direction::KING
+                .iter()
+                .fold(0, |destinations, dir| destinations | b.shift_dir(*dir));
             v.push(r);
         }
 
@@ -183,35 +190,109 @@ pub struct MoveGenerator {
 }
 
 impl MoveGenerator {
+    #[must_use]
     pub fn new() -> MoveGenerator {
         let random = MagicRandomizer::new(GenerationScheme::PreComputed);
         let lookup = Lookup::new(random);
         MoveGenerator { lookup }
     }
 
+    /// Construct a move generator whose magic numbers are generated at runtime from the given
+    /// seed instead of being read from the precomputed table. This is much slower than `new`, but
+    /// exercises magic number generation from start to finish.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn new_pseudo_random(seed: u64) -> MoveGenerator {
+        let random = MagicRandomizer::from_seed(seed);
+        let lookup = Lookup::new(random);
+        MoveGenerator { lookup }
+    }
+
+    #[must_use]
     pub fn all_moves(&self, pos: &BoardState) -> Vec<Move> {
         let mut list: Vec<Move> = Vec::with_capacity(MAX_MOVES);
 
-        gen_pseudo_legal_pawn_moves(pos, &mut list);
-        gen_pseudo_legal_castles(pos, &mut list);
-
-        self.gen_pseudo_legal_moves(pos, &mut list, PieceType::Knight);
-        self.gen_pseudo_legal_moves(pos, &mut list, PieceType::Rook);
-        self.gen_pseudo_legal_moves(pos, &mut list, PieceType::Bishop);
-        self.gen_pseudo_legal_moves(pos, &mut list, PieceType::Queen);
-        self.gen_pseudo_legal_moves(pos, &mut list, PieceType::King);
-
         let king_square = king_square(pos);
         let blockers = self.calculate_blockers(pos, king_square);
         let checkers = self.attacks_to(pos, king_square);
 
+        if checkers == 0 {
+            self.gen_pseudo_legal(pos, &mut list);
+        } else {
+            self.gen_evasions(pos, &mut list, king_square, checkers);
+        }
+
         list.retain(|mv| self.is_legal(pos, mv, blockers, checkers, king_square));
 
         list
     }
 
+    /// Return all pseudo-legal moves from the given position, without removing those which leave
+    /// the king in check, castle out of or through check, or move a pinned piece off its line.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn pseudo_legal_moves(&self, pos: &BoardState) -> Vec<Move> {
+        let mut list: Vec<Move> = Vec::with_capacity(MAX_MOVES);
+        self.gen_pseudo_legal(pos, &mut list);
+        list
+    }
+
+    /// Generate all pseudo-legal moves for the given position.
+    fn gen_pseudo_legal(&self, pos: &BoardState, list: &mut Vec<Move>) {
+        gen_pseudo_legal_pawn_moves(pos, list);
+        gen_pseudo_legal_castles(pos, list);
+
+        self.gen_pseudo_legal_moves(pos, list, PieceType::Knight);
+        self.gen_pseudo_legal_moves(pos, list, PieceType::Rook);
+        self.gen_pseudo_legal_moves(pos, list, PieceType::Bishop);
+        self.gen_pseudo_legal_moves(pos, list, PieceType::Queen);
+        self.gen_pseudo_legal_moves(pos, list, PieceType::King);
+    }
+
+    /// Generate pseudo-legal moves which might get the king out of check, assuming that the king
+    /// is attacked by the given checkers. These are king moves, and if there is only a single
+    /// checker, captures of that checker and interpositions along the checking ray.
+    fn gen_evasions(
+        &self,
+        pos: &BoardState,
+        list: &mut Vec<Move>,
+        king_square: Square,
+        checkers: Bitboard,
+    ) {
+        self.gen_pseudo_legal_moves(pos, list, PieceType::King);
+
+        // With more than one checker only the king can move
+        if checkers.count_ones() > 1 {
+            return;
+        }
+
+        let checker_square = checkers.trailing_zeros() as Square;
+        let targets =
+            self.ray_between(king_square, checker_square) & !self.lookup.square_bb(king_square);
+
+        let start = list.len();
+        gen_pseudo_legal_pawn_moves(pos, list);
+        // An en passant capture lands behind the checking pawn rather than on it, so it is
+        // kept here and left for the legality check to decide.
+        let mut i = start;
+        while i < list.len() {
+            let mv = list[i];
+            if targets & self.lookup.square_bb(mv.to) == 0 && !mv.is_en_passant_capture() {
+                list.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        self.gen_targeted_moves(pos, list, PieceType::Knight, targets);
+        self.gen_targeted_moves(pos, list, PieceType::Rook, targets);
+        self.gen_targeted_moves(pos, list, PieceType::Bishop, targets);
+        self.gen_targeted_moves(pos, list, PieceType::Queen, targets);
+    }
+
     /// Given the state of a game, calculates and returns a bitboard which represents all blockers
     /// (i.e. pinned pieces) for the king.
+    #[must_use]
     pub fn calculate_blockers(&self, pos: &BoardState, king_square: Square) -> Bitboard {
         let us = pos.active_player;
         let king_bb = pos.bb(us, PieceType::King);
@@ -226,7 +307,7 @@ impl MoveGenerator {
 
         let mut blockers = Bitboard::empty();
 
-        for (i, _) in snipers.iter() {
+        for i in snipers.squares() {
             let ignore = self.lookup.square_bb(i);
             let potential_blockers =
                 self.ray_between(king_square, i) & occupancy & !king_bb & !ignore;
@@ -240,6 +321,7 @@ impl MoveGenerator {
     }
 
     /// Returns a bitboard representing all pieces which are attacking the provided square.
+    #[must_use]
     pub fn attacks_to(&self, pos: &BoardState, square: Square) -> Bitboard {
         let us = pos.active_player;
         let occupancies = pos.bb_all() & !pos.bb(us, PieceType::King);
@@ -272,10 +354,7 @@ impl MoveGenerator {
         let us = pos.active_player;
         let mut pos = *pos;
 
-        let offset: i8 = match us {
-            Color::White => 8,
-            Color::Black => -8,
-        };
+        let offset = us.pawn_push_offset();
 
         pos.remove_piece(PieceType::Pawn, !us, (mv.to as i8 - offset) as u8);
         let tmp_mv = Move {
@@ -345,6 +424,7 @@ impl MoveGenerator {
         self.is_legal_pin_move(pos, mv)
     }
 
+    #[must_use]
     pub fn is_attacked(&self, pos: &BoardState, square: Square) -> bool {
         let us = pos.active_player;
 
@@ -389,19 +469,11 @@ impl MoveGenerator {
             return false;
         }
 
-        let squares: Vec<Square> = match mv.kind {
-            MoveType::CastleKing => match pos.active_player {
-                Color::White => vec![5, 6],
-                Color::Black => vec![61, 62],
-            },
-            MoveType::CastleQueen => match pos.active_player {
-                Color::White => vec![2, 3],
-                Color::Black => vec![58, 59],
-            },
-            _ => vec![],
+        let Some(side) = CastleSide::from_move_type(mv.kind) else {
+            return false;
         };
 
-        for square in squares {
+        for square in side.squares(pos.active_player).king_path().squares() {
             if self.is_attacked(pos, square) {
                 return false;
             }
@@ -412,6 +484,7 @@ impl MoveGenerator {
 
     /// Determines whether or not the given move is legal given the provided state of the game.
     /// A move is determined to be legal if it does not leave the king in check after the move is made.
+    #[must_use]
     pub fn is_legal(
         &self,
         pos: &BoardState,
@@ -472,16 +545,29 @@ impl MoveGenerator {
     }
 
     pub fn gen_pseudo_legal_moves(&self, pos: &BoardState, list: &mut Vec<Move>, piece: PieceType) {
+        self.gen_targeted_moves(pos, list, piece, !Bitboard::empty());
+    }
+
+    /// Like `gen_pseudo_legal_moves`, except that only moves whose destination is within the
+    /// given targets are generated.
+    fn gen_targeted_moves(
+        &self,
+        pos: &BoardState,
+        list: &mut Vec<Move>,
+        piece: PieceType,
+        targets: Bitboard,
+    ) {
         let us = pos.active_player;
         let pieces = pos.bb(us, piece);
         let valid_pieces = pos.bb_for_color(!us);
         let empty_squares = !pos.bb_all();
 
-        for (square, _) in pieces.iter() {
+        for square in pieces.squares() {
             let destinations = match piece {
                 PieceType::King | PieceType::Knight => self.lookup.moves(square, piece),
                 _ => self.lookup.sliding_moves(square, pos.bb_all(), piece),
             };
+            let destinations = destinations & targets;
             let captures = destinations & valid_pieces;
             let quiets = destinations & empty_squares;
 
@@ -490,12 +576,14 @@ impl MoveGenerator {
         }
     }
 
+    #[must_use]
     pub fn is_in_check(&self, pos: &BoardState) -> bool {
         let king_square = king_square(pos);
         let checkers: Bitboard = self.attacks_to(pos, king_square);
         checkers.count_ones() != 0
     }
 
+    #[allow(dead_code)]
     pub fn is_giving_check(&self, pos: &mut BoardState) -> bool {
         pos.switch();
         let king_square = king_square(pos);
@@ -504,7 +592,155 @@ impl MoveGenerator {
         checkers.count_ones() != 0
     }
 
+    /// Statically evaluate the exchange started by the given move, returning the net material won
+    /// by the moving side in centipawns once both sides have recaptured on the destination square
+    /// for as long as it is profitable. Each capture is made with the least valuable attacker,
+    /// and pieces which attack the square through a piece that has already captured (such as a
+    /// rook behind a bishop) join the exchange once that piece has left.
+    #[allow(dead_code)]
+    pub fn see(&self, pos: &BoardState, mv: Move) -> isize {
+        let mut occupancy = pos.bb_all() & !self.lookup.square_bb(mv.from);
+        let Some(mut on_square) = pos.type_on(mv.from) else {
+            return 0;
+        };
+        let mut gains = vec![if mv.is_en_passant_capture() {
+            let captured = mv
+                .to
+                .wrapping_add_signed(-pos.active_player.pawn_push_offset());
+            occupancy &= !self.lookup.square_bb(captured);
+            exchange_value(PieceType::Pawn)
+        } else {
+            pos.type_on(mv.to).map_or(0, exchange_value)
+        }];
+        if let Some(promoted) = mv.promoted_piece() {
+            gains[0] += exchange_value(promoted) - exchange_value(PieceType::Pawn);
+            on_square = promoted;
+        }
+
+        let mut side = !pos.active_player;
+        loop {
+            let attackers = self.attackers_with_occupancy(pos, mv.to, occupancy)
+                & occupancy
+                & pos.bb_for_color(side);
+            let next = EXCHANGE_ORDER
+                .iter()
+                .map(|piece| (*piece, attackers & pos.bb_pieces(*piece)))
+                .find(|(_, bb)| *bb != 0);
+            let Some((piece, bb)) = next else {
+                break;
+            };
+
+            gains.push(exchange_value(on_square) - gains[gains.len() - 1]);
+            occupancy &= !(bb & bb.wrapping_neg());
+            on_square = piece;
+            side = !side;
+        }
+
+        // Each side only recaptures if doing so is better than ending the exchange.
+        for i in (1..gains.len()).rev() {
+            gains[i - 1] = gains[i - 1].min(-gains[i]);
+        }
+        gains[0]
+    }
+
+    /// Returns a bitboard of the pieces of either color which attack the given square, with
+    /// sliding attacks computed as if only the squares in `occupancy` were occupied.
+    fn attackers_with_occupancy(
+        &self,
+        pos: &BoardState,
+        square: Square,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let rook_attacks = self
+            .lookup
+            .sliding_moves(square, occupancy, PieceType::Rook);
+        let bishop_attacks = self
+            .lookup
+            .sliding_moves(square, occupancy, PieceType::Bishop);
+
+        (pawn_attacks(square, Color::White) & pos.bb(Color::Black, PieceType::Pawn))
+            | (pawn_attacks(square, Color::Black) & pos.bb(Color::White, PieceType::Pawn))
+            | (rook_attacks & (pos.bb_pieces(PieceType::Rook) | pos.bb_pieces(PieceType::Queen)))
+            | (bishop_attacks
+                & (pos.bb_pieces(PieceType::Bishop) | pos.bb_pieces(PieceType::Queen)))
+            | (self.lookup.moves(square, PieceType::Knight) & pos.bb_pieces(PieceType::Knight))
+            | (self.lookup.moves(square, PieceType::King) & pos.bb_pieces(PieceType::King))
+    }
+
+    /// Returns a bitboard of every square attacked by the given color's pieces, whether or not
+    /// it is occupied. The opposing king does not block sliding pieces, so the squares behind it
+    /// on a line of attack count as attacked, as they do when deciding where that king may move.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn attack_map(&self, pos: &BoardState, color: Color) -> Bitboard {
+        let occupancy = pos.bb_all() & !pos.bb(!color, PieceType::King);
+        let mut attacks: Bitboard = 0;
+        for piece in PieceType::iterator() {
+            for square in pos.bb(color, *piece).squares() {
+                attacks |= self.piece_attacks(square, *piece, color, occupancy);
+            }
+        }
+        attacks
+    }
+
+    /// Returns the number of pieces, other than the king, of the opponent of the given color
+    /// which attack the king of that color or any square next to it. As in `attack_map`, the
+    /// king does not block sliding pieces from the squares behind it.
     #[allow(dead_code)]
+    #[must_use]
+    pub fn king_attackers(&self, pos: &BoardState, color: Color) -> u32 {
+        let Some(king) = pos.king_square(color) else {
+            return 0;
+        };
+        let zone = self.lookup.moves(king, PieceType::King) | self.lookup.square_bb(king);
+        let occupancy = pos.bb_all() & !pos.bb(color, PieceType::King);
+
+        let mut attackers = 0;
+        for piece in PieceType::iterator().filter(|piece| **piece != PieceType::King) {
+            for square in pos.bb(!color, *piece).squares() {
+                if self.piece_attacks(square, *piece, !color, occupancy) & zone != 0 {
+                    attackers += 1;
+                }
+            }
+        }
+        attackers
+    }
+
+    /// Returns the squares attacked by a piece of the given color on the given square.
+    fn piece_attacks(
+        &self,
+        square: Square,
+        piece: PieceType,
+        color: Color,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        match piece {
+            PieceType::Pawn => pawn_attacks(square, color),
+            PieceType::Knight | PieceType::King => self.lookup.moves(square, piece),
+            _ => self.lookup.sliding_moves(square, occupancy, piece),
+        }
+    }
+
+    /// Returns a bitboard of the active player's pieces which are pinned to their king, or an
+    /// empty bitboard if the active player has no king.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn pinned_pieces(&self, pos: &BoardState) -> Bitboard {
+        pos.king_square(pos.active_player).map_or(0, |king| {
+            self.calculate_blockers(pos, king) & pos.bb_for_color(pos.active_player)
+        })
+    }
+
+    /// Returns a bitboard of the opposing pieces which give check to the active player's king.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn checkers(&self, pos: &BoardState) -> Bitboard {
+        pos.king_square(pos.active_player)
+            .map_or(0, |king| self.attacks_to(pos, king))
+    }
+
+    #[allow(dead_code)]
+    #[must_use]
     pub fn perft(&self, pos: &BoardState, depth: usize) -> usize {
         self.perft_inner(pos, depth)
     }
@@ -522,53 +758,89 @@ impl MoveGenerator {
             sum
         }
     }
-}
 
-pub fn gen_pseudo_legal_castles(pos: &BoardState, list: &mut Vec<Move>) {
-    let us = pos.active_player;
+    /// Like `perft`, except that leaf moves are also classified by kind so that the results can be
+    /// compared against published perft tables.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn perft_with_stats(&self, pos: &BoardState, depth: usize) -> PerftStats {
+        let mut stats = PerftStats::default();
+        self.perft_with_stats_inner(pos, depth, &mut stats);
+        stats
+    }
 
-    let (king_mask, queen_mask) = match us {
-        Color::White => (96, 14),
-        Color::Black => (6_917_529_027_641_081_856, 1_008_806_316_530_991_104),
-    };
+    fn perft_with_stats_inner(&self, pos: &BoardState, depth: usize, stats: &mut PerftStats) {
+        let moves = self.all_moves(pos);
+        for mv in moves {
+            let new_pos = pos.clone_with_move(mv);
+            if depth == 1 {
+                stats.count(mv, self.is_in_check(&new_pos));
+            } else {
+                self.perft_with_stats_inner(&new_pos, depth - 1, stats);
+            }
+        }
+    }
+}
 
-    let occupied = pos.bb_all();
+impl Default for MoveGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    let (king_rights, queen_rights) = match us {
-        Color::White => (
-            pos.castling_rights.white_king,
-            pos.castling_rights.white_queen,
-        ),
-        Color::Black => (
-            pos.castling_rights.black_king,
-            pos.castling_rights.black_queen,
-        ),
-    };
+/// A breakdown of the leaf nodes visited during a perft run.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct PerftStats {
+    pub nodes: usize,
+    pub captures: usize,
+    pub en_passants: usize,
+    pub castles: usize,
+    pub promotions: usize,
+    pub checks: usize,
+}
 
-    if (occupied & king_mask == 0) && king_rights {
-        let (to, from) = match us {
-            Color::White => (G1 as u8, E1 as u8),
-            Color::Black => (G8 as u8, E8 as u8),
-        };
-        let m = Move {
-            to,
-            from,
-            kind: MoveType::CastleKing,
-        };
-        list.push(m);
+impl PerftStats {
+    fn count(&mut self, mv: Move, gives_check: bool) {
+        self.nodes += 1;
+        if mv.is_capture() {
+            self.captures += 1;
+        }
+        if mv.is_en_passant_capture() {
+            self.en_passants += 1;
+        }
+        if mv.is_castle() {
+            self.castles += 1;
+        }
+        if mv.is_promotion() || mv.is_promotion_capture() {
+            self.promotions += 1;
+        }
+        if gives_check {
+            self.checks += 1;
+        }
     }
+}
 
-    if (occupied & queen_mask == 0) && queen_rights {
-        let (to, from) = match us {
-            Color::White => (C1 as u8, E1 as u8),
-            Color::Black => (C8 as u8, E8 as u8),
-        };
-        let m = Move {
-            to,
-            from,
-            kind: MoveType::CastleQueen,
-        };
-        list.push(m);
+pub fn gen_pseudo_legal_castles(pos: &BoardState, list: &mut Vec<Move>) {
+    let us = pos.active_player;
+    let occupied = pos.bb_all();
+    let rooks = pos.bb(us, PieceType::Rook);
+    let king = pos.bb(us, PieceType::King);
+
+    // Castling rights read from a FEN may not match the board, so the king and rook must also be
+    // on their starting squares.
+    for side in &CastleSide::ALL {
+        let squares = side.squares(us);
+        if pos.castling_rights.allows(us, *side)
+            && occupied & squares.empty_squares() == 0
+            && rooks & Bitboard::for_square(squares.rook_from) != 0
+            && king & Bitboard::for_square(squares.king_from) != 0
+        {
+            list.push(Move {
+                to: squares.king_to,
+                from: squares.king_from,
+                kind: side.move_type(),
+            });
+        }
     }
 }
 
@@ -578,7 +850,7 @@ pub fn king_square(pos: &BoardState) -> Square {
 }
 
 pub fn extract_moves(from: u8, bb: Bitboard, list: &mut Vec<Move>, kind: MoveType) {
-    for (square, _) in bb.iter() {
+    for square in bb.squares() {
         let m = Move {
             to: square,
             from,
@@ -589,45 +861,12 @@ pub fn extract_moves(from: u8, bb: Bitboard, list: &mut Vec<Move>, kind: MoveTyp
 }
 
 pub fn knight_destinations(square: u8) -> Bitboard {
-    let base_bb: Bitboard = 0;
-    let base_bb = base_bb.add_at_square(square);
-
-    let nnw = base_bb
-        .checked_shl((NORTH + NORTH + WEST) as u32)
-        .unwrap_or(0)
-        & !FILEH;
-    let nww = base_bb
-        .checked_shl((NORTH + WEST + WEST) as u32)
-        .unwrap_or(0)
-        & !(FILEH | FILEG);
-    let nne = base_bb
-        .checked_shl((NORTH + NORTH + EAST) as u32)
-        .unwrap_or(0)
-        & !FILEA;
-    let nee = base_bb
-        .checked_shl((NORTH + EAST + EAST) as u32)
-        .unwrap_or(0)
-        & !(FILEA | FILEB);
-
-    let sww = base_bb
-        .checked_shr(-(SOUTH + WEST + WEST) as u32)
-        .unwrap_or(0)
-        & !(FILEG | FILEH);
-    let ssw = base_bb
-        .checked_shr(-(SOUTH + SOUTH + WEST) as u32)
-        .unwrap_or(0)
-        & !FILEH;
-    let sse = base_bb
-        .checked_shr(-(SOUTH + SOUTH + EAST) as u32)
-        .unwrap_or(0)
-        & !FILEA;
-    let see = base_bb
-        .checked_shr(-(SOUTH + EAST + EAST) as u32)
-        .unwrap_or(0)
-        & !(FILEA | FILEB);
-
-    nnw | nww | nne | nee | sww | ssw | sse | see
+    let base_bb = Bitboard::for_square(square);
+    Direction::KNIGHT.iter().fold(0, |destinations, dir| {
+        destinations | base_bb.shift_dir(*dir)
+    })
 }
+
 #[derive(Copy, Clone)]
 struct PawnDirections {
     rank7: Bitboard,
@@ -721,7 +960,7 @@ fn gen_promotions(pos: &BoardState, list: &mut Vec<Move>, dirs: PawnDirections,
 
 /// Given a resulting bitboard and a relevant offset, find all pawn moves using the given offset.
 pub fn extract_pawn_moves(bitboard: Bitboard, offset: i8, kind: MoveType, moves: &mut Vec<Move>) {
-    for (square, _) in bitboard.iter() {
+    for square in bitboard.squares() {
         let m = Move {
             to: square as u8,
             from: (square as i8 - offset) as u8,
@@ -731,19 +970,29 @@ pub fn extract_pawn_moves(bitboard: Bitboard, offset: i8, kind: MoveType, moves:
     }
 }
 
+/// The value of a piece in a static exchange evaluation. The king is worth more than everything
+/// else combined, so that capturing with it into a defended square is never worthwhile.
+fn exchange_value(piece: PieceType) -> isize {
+    match piece {
+        PieceType::Pawn => 100,
+        PieceType::Knight | PieceType::Bishop => 300,
+        PieceType::Rook => 500,
+        PieceType::Queen => 800,
+        PieceType::King => 10_000,
+    }
+}
+
 /// Returns a bitboard representing all pawn attacks from the given square for the given color
 pub fn pawn_attacks(square: Square, color: Color) -> Bitboard {
     let b: Bitboard = 0;
     let b = b.add_at_square(square);
-    match color {
-        Color::White => b.shift(NORTH + WEST) | b.shift(NORTH + EAST),
-        Color::Black => b.shift(SOUTH + WEST) | b.shift(SOUTH + EAST),
-    }
+    let north = color.pawn_push_offset();
+    b.shift(north + WEST) | b.shift(north + EAST)
 }
 
 /// Given a resulting bitboard, find and enumerate all possible promotions using the provided offset.
 fn extract_promotions(bitboard: Bitboard, offset: i8, moves: &mut Vec<Move>, kind: PromotionType) {
-    for (square, _) in bitboard.iter() {
+    for square in bitboard.squares() {
         let itr = match kind {
             PromotionType::Push => MoveType::promotion_itr(),
             PromotionType::Capture => MoveType::promotion_capture_itr(),
@@ -771,68 +1020,33 @@ fn en_passant_bb(pos: &BoardState) -> Bitboard {
 
 impl PawnDirections {
     fn new(color: Color) -> PawnDirections {
-        let rank7 = match color {
-            Color::White => RANK7,
-            Color::Black => RANK2,
-        };
-        let rank3 = match color {
-            Color::White => RANK3,
-            Color::Black => RANK6,
-        };
-        let north = match color {
-            Color::White => NORTH,
-            Color::Black => SOUTH,
-        };
+        let north = color.pawn_push_offset();
         PawnDirections {
-            rank7,
-            rank3,
+            rank7: color.promotion_rank().shift(-north),
+            rank3: color.en_passant_rank(),
             north,
         }
     }
 }
 
 #[allow(dead_code)]
+#[deprecated(note = "use the `Display` implementation of `BoardState` instead")]
 pub fn debug_print(pos: &BoardState) -> String {
-    let mut s = String::with_capacity(64);
-    for i in 0..8 {
-        for j in 0..8 {
-            let file = j;
-            let rank = 7 - i;
-            let square = rank_file_to_index(rank, file);
-            let piece = pos.type_on(square);
-            let mut c;
-            if piece.is_none() {
-                c = '.';
-            } else {
-                c = match piece.unwrap() {
-                    PieceType::Pawn => 'p',
-                    PieceType::Rook => 'r',
-                    PieceType::Knight => 'n',
-                    PieceType::Bishop => 'b',
-                    PieceType::King => 'k',
-                    PieceType::Queen => 'q',
-                };
-                if pos.color_on(square).unwrap() == Color::White {
-                    c = c.to_ascii_uppercase();
-                }
-            }
-            print!("{}", c);
-            s.push(c);
-        }
-        println!();
-    }
-    println!("{}", s);
-    s
+    pos.to_string()
 }
 
 #[cfg(test)]
 mod test {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
     use super::*;
     use crate::bitboard::RANK2;
     use crate::board::BoardState;
-    use crate::chess_move::Move;
     use crate::chess_move::MoveType::Quiet;
-    use crate::fen::parse_fen;
+    use crate::chess_move::{Move, NORTH};
+    use crate::fen::{parse_fen, to_fen};
     use crate::magic::{GenerationScheme, MagicRandomizer};
     use crate::move_gen::{gen_pseudo_legal_castles, king_square, MoveGenerator};
     use crate::square::SquareIndex;
@@ -857,6 +1071,16 @@ mod test {
         assert_eq!(depth_3, 8902);
         assert_eq!(depth_4, 197_281);
     }
+    #[test]
+    #[ignore]
+    fn perft_kiwipete_with_generated_magics() {
+        let pos = parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+        let gen = MoveGenerator::new_pseudo_random(7);
+        assert_eq!(gen.perft(&pos, 1), 48);
+        assert_eq!(gen.perft(&pos, 2), 2039);
+    }
+
     #[test]
     #[ignore]
     fn perft_kiwipete() {
@@ -951,7 +1175,7 @@ mod test {
     #[ignore]
     fn perft_fen_random() {
         let mut pos =
-            parse_fen("r6r/1bp2pP1/R2qkn2/1P6/1pPQ4/1B3N2/1B1P2p1/4K2R b KQ c3 0 1").unwrap();
+            parse_fen("r6r/1bp2pP1/R2qkn2/1P6/1pPQ4/1B3N2/1B1P2p1/4K2R b K c3 0 1").unwrap();
 
         let gen = MoveGenerator::new();
         let depth_1 = gen.perft(&mut pos, 1);
@@ -959,8 +1183,66 @@ mod test {
         let depth_3 = gen.perft(&mut pos, 3);
 
         assert_eq!(depth_1, 51);
-        assert_eq!(depth_2, 2778);
-        assert_eq!(depth_3, 111_425);
+        assert_eq!(depth_2, 2733);
+        assert_eq!(depth_3, 109_269);
+    }
+
+    #[test]
+    fn perft_with_stats_kiwipete() {
+        let pos = parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+        let gen = MoveGenerator::new();
+
+        let depth_1 = gen.perft_with_stats(&pos, 1);
+        assert_eq!(depth_1.nodes, 48);
+        assert_eq!(depth_1.captures, 8);
+        assert_eq!(depth_1.en_passants, 0);
+        assert_eq!(depth_1.castles, 2);
+        assert_eq!(depth_1.promotions, 0);
+        assert_eq!(depth_1.checks, 0);
+
+        let depth_2 = gen.perft_with_stats(&pos, 2);
+        assert_eq!(depth_2.nodes, 2039);
+        assert_eq!(depth_2.captures, 351);
+        assert_eq!(depth_2.en_passants, 1);
+        assert_eq!(depth_2.castles, 91);
+        assert_eq!(depth_2.promotions, 0);
+        assert_eq!(depth_2.checks, 3);
+    }
+
+    #[test]
+    fn evasions_match_general_generation() {
+        let gen = MoveGenerator::new();
+        let fens = [
+            "2r5/8/8/2B5/8/8/8/2K3r1 w - - 0 1",
+            "8/8/8/8/1K5r/8/8/2k5 w - - 0 1",
+            "4k3/8/8/8/4r3/8/3P1P2/2B1K1N1 w - - 0 1",
+            "4k3/8/8/8/1b6/8/8/R3K2R w KQ - 0 1",
+            "4k3/8/8/8/8/3n4/8/r3K3 w - - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            "rnbqkbnr/ppppp1pp/8/5p1Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 0 1",
+            "3k4/3q4/8/8/8/8/2P1P3/3K4 w - - 0 1",
+            "6k1/5P2/8/8/8/8/8/4K3 b - - 0 1",
+            "r6k/6P1/8/8/8/8/8/4K3 b - - 0 1",
+        ];
+
+        for fen in fens.iter() {
+            let pos = parse_fen(fen).unwrap();
+            let king_square = king_square(&pos);
+            let blockers = gen.calculate_blockers(&pos, king_square);
+            let checkers = gen.attacks_to(&pos, king_square);
+            assert_ne!(checkers, 0, "{}", fen);
+
+            let mut general = Vec::new();
+            gen.gen_pseudo_legal(&pos, &mut general);
+            general.retain(|mv| gen.is_legal(&pos, mv, blockers, checkers, king_square));
+
+            let mut moves = gen.all_moves(&pos);
+            let key = |mv: &Move| (mv.from, mv.to, mv.kind as u8);
+            general.sort_by_key(key);
+            moves.sort_by_key(key);
+            assert_eq!(moves, general, "{}", fen);
+        }
     }
 
     #[test]
@@ -1275,7 +1557,7 @@ mod test {
     fn random_fen_4() {
         let gen = MoveGenerator::new();
         let pos =
-            parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R w KQkq a3 0 1")
+            parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R b KQkq a3 0 1")
                 .unwrap();
         let mv = Move {
             to: A3 as u8,
@@ -1588,4 +1870,94 @@ mod test {
         pos.make_move(mv);
         assert_eq!(pos.bb_all(), 65536)
     }
+
+    /// A slow but simple legality check to compare `all_moves` against: the move is made, and
+    /// it is legal if the mover's king is not attacked afterwards. Castling additionally
+    /// requires that the king does not start on or pass through an attacked square.
+    fn is_legal_by_making(gen: &MoveGenerator, pos: &BoardState, mv: Move) -> bool {
+        if let Some(side) = CastleSide::from_move_type(mv.kind) {
+            let squares = side.squares(pos.active_player);
+            let path = squares.king_path() | 1 << squares.king_from;
+            if gen.attack_map(pos, !pos.active_player) & path != 0 {
+                return false;
+            }
+        }
+
+        let mut new_pos = pos.clone_with_move(mv);
+        new_pos.switch();
+        !gen.is_in_check(&new_pos)
+    }
+
+    #[test]
+    fn legal_moves_match_reference_in_random_games() {
+        let gen = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(2185);
+        let mut positions = 0;
+        for _ in 0..100 {
+            let mut pos = BoardState::default();
+            for _ in 0..200 {
+                let mut fast = gen.all_moves(&pos);
+                let mut slow: Vec<Move> = gen
+                    .pseudo_legal_moves(&pos)
+                    .into_iter()
+                    .filter(|mv| is_legal_by_making(&gen, &pos, *mv))
+                    .collect();
+                fast.sort_by_key(|mv| (mv.from, mv.to, mv.kind as u8));
+                slow.sort_by_key(|mv| (mv.from, mv.to, mv.kind as u8));
+                assert_eq!(fast, slow, "{}", to_fen(&pos));
+                positions += 1;
+
+                let Some(mv) = fast.choose(&mut rng) else {
+                    break;
+                };
+                pos.make_move(*mv);
+            }
+        }
+        assert!(positions > 5000);
+    }
+
+    #[test]
+    fn knight_destinations_from_corners() {
+        let a1 = knight_destinations(0);
+        assert_eq!(a1, Bitboard::for_square(10) | Bitboard::for_square(17));
+
+        let h8 = knight_destinations(63);
+        assert_eq!(h8, Bitboard::for_square(46) | Bitboard::for_square(53));
+    }
+
+    #[test]
+    fn generates_moves_for_maximal_mobility_position() {
+        let gen = MoveGenerator::new();
+        let pos = parse_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        let moves = gen.all_moves(&pos);
+        assert_eq!(moves.len(), 218);
+        assert!(moves.len() <= MAX_MOVES);
+
+        let mut unique = moves.clone();
+        unique.sort_by_key(|mv| (mv.from, mv.to));
+        unique.dedup();
+        assert_eq!(unique.len(), moves.len());
+    }
+
+    #[test]
+    fn pinned_pawn_cannot_promote_off_pin() {
+        let gen = MoveGenerator::new();
+        let pawn_moves = |fen: &str| -> Vec<Move> {
+            let pos = parse_fen(fen).unwrap();
+            gen.all_moves(&pos)
+                .into_iter()
+                .filter(|mv| mv.from == 49)
+                .collect()
+        };
+
+        // The rook on a7 pins the pawn to the king along the seventh rank.
+        assert!(pawn_moves("n1b5/rP5K/8/5k2/8/8/8/8 w - - 0 1").is_empty());
+
+        // Along a diagonal pin, the pawn may only promote by capturing the pinning bishop.
+        let moves = pawn_moves("b1n5/1P6/2K5/8/8/8/8/7k w - - 0 1");
+        assert_eq!(moves.len(), 4);
+        assert!(moves
+            .iter()
+            .all(|mv| mv.to == 56 && mv.is_promotion_capture()));
+    }
 }
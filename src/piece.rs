@@ -1,11 +1,30 @@
 use std::ops::{Index, IndexMut, Not};
 use std::slice::Iter;
 
-use crate::bitboard::Bitboard;
+use crate::bitboard::{Bitboard, RANK1, RANK3, RANK6, RANK8};
 
 pub const PIECE_COUNT: usize = 6;
 pub const COLOR_COUNT: usize = 2;
 
+const PAWN_VALUE: isize = 100;
+const ROOK_VALUE: isize = 500;
+const KNIGHT_VALUE: isize = 300;
+const BISHOP_VALUE: isize = 300;
+const KING_VALUE: isize = 350;
+const QUEEN_VALUE: isize = 800;
+
+const PAWN_ENDGAME_VALUE: isize = 110;
+const ROOK_ENDGAME_VALUE: isize = 550;
+const KNIGHT_ENDGAME_VALUE: isize = 280;
+const BISHOP_ENDGAME_VALUE: isize = 320;
+const KING_ENDGAME_VALUE: isize = 350;
+const QUEEN_ENDGAME_VALUE: isize = 850;
+
+const KNIGHT_PHASE: isize = 1;
+const BISHOP_PHASE: isize = 1;
+const ROOK_PHASE: isize = 2;
+const QUEEN_PHASE: isize = 4;
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum PieceType {
     Pawn,
@@ -55,8 +74,8 @@ impl IndexMut<PieceType> for [Bitboard; PIECE_COUNT] {
     }
 }
 
-impl Index<Color> for [Bitboard; COLOR_COUNT] {
-    type Output = Bitboard;
+impl<T> Index<Color> for [T; COLOR_COUNT] {
+    type Output = T;
 
     fn index(&self, color: Color) -> &Self::Output {
         match color {
@@ -66,7 +85,7 @@ impl Index<Color> for [Bitboard; COLOR_COUNT] {
     }
 }
 
-impl IndexMut<Color> for [Bitboard; COLOR_COUNT] {
+impl<T> IndexMut<Color> for [T; COLOR_COUNT] {
     fn index_mut(&mut self, color: Color) -> &mut Self::Output {
         match color {
             Color::White => &mut self[0],
@@ -110,6 +129,20 @@ impl PieceType {
         PIECES.iter()
     }
 
+    /// Returns the lowercase character used for this piece in FEN strings.
+    #[must_use]
+    pub fn to_char(self) -> char {
+        match self {
+            PieceType::Pawn => 'p',
+            PieceType::Rook => 'r',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::King => 'k',
+            PieceType::Queen => 'q',
+        }
+    }
+
+    #[must_use]
     pub fn value(&self) -> i64 {
         match self {
             PieceType::Pawn => 1,
@@ -120,6 +153,51 @@ impl PieceType {
         }
     }
 
+    /// Returns the value of this piece in centipawns, as counted by the material evaluation.
+    #[must_use]
+    pub fn material_value(self) -> isize {
+        match self {
+            PieceType::Pawn => PAWN_VALUE,
+            PieceType::Rook => ROOK_VALUE,
+            PieceType::Knight => KNIGHT_VALUE,
+            PieceType::Bishop => BISHOP_VALUE,
+            PieceType::King => KING_VALUE,
+            PieceType::Queen => QUEEN_VALUE,
+        }
+    }
+
+    /// Returns the value of this piece in centipawns once all non-pawn material has been traded
+    /// off. Rooks and queens gain value as the board opens up, while knights lose some of theirs.
+    #[must_use]
+    pub fn endgame_value(self) -> isize {
+        match self {
+            PieceType::Pawn => PAWN_ENDGAME_VALUE,
+            PieceType::Rook => ROOK_ENDGAME_VALUE,
+            PieceType::Knight => KNIGHT_ENDGAME_VALUE,
+            PieceType::Bishop => BISHOP_ENDGAME_VALUE,
+            PieceType::King => KING_ENDGAME_VALUE,
+            PieceType::Queen => QUEEN_ENDGAME_VALUE,
+        }
+    }
+
+    /// Returns how much this piece contributes to the phase of the game. Pawns and kings do not
+    /// count, so that only the non-pawn material left on the board decides the phase.
+    #[must_use]
+    pub fn phase(self) -> isize {
+        match self {
+            PieceType::Pawn | PieceType::King => 0,
+            PieceType::Knight => KNIGHT_PHASE,
+            PieceType::Bishop => BISHOP_PHASE,
+            PieceType::Rook => ROOK_PHASE,
+            PieceType::Queen => QUEEN_PHASE,
+        }
+    }
+
+    /// Returns the index of this piece in the MVV-LVA table, ordered from the most to the least
+    /// valuable piece: King (0), Queen, Rook, Bishop, Knight, Pawn (5). This ordering is relied on
+    /// by `MVV_LVA` and must not change. Note that the `Position` bitboard arrays are indexed
+    /// through their own `Index` implementation instead, which uses a different order.
+    #[must_use]
     pub fn idx(&self) -> usize {
         match self {
             PieceType::Pawn => 5,
@@ -130,6 +208,20 @@ impl PieceType {
             PieceType::Queen => 1,
         }
     }
+
+    /// The inverse of `idx`, returning `None` for indices outside of 0–5.
+    #[must_use]
+    pub fn from_idx(idx: usize) -> Option<PieceType> {
+        match idx {
+            0 => Some(PieceType::King),
+            1 => Some(PieceType::Queen),
+            2 => Some(PieceType::Rook),
+            3 => Some(PieceType::Bishop),
+            4 => Some(PieceType::Knight),
+            5 => Some(PieceType::Pawn),
+            _ => None,
+        }
+    }
 }
 
 impl Color {
@@ -137,6 +229,43 @@ impl Color {
         static COLORS: [Color; 2] = [Color::White, Color::Black];
         COLORS.iter()
     }
+
+    /// Returns the square offset of a single pawn push for this color.
+    #[must_use]
+    pub fn pawn_push_offset(self) -> i8 {
+        match self {
+            Color::White => 8,
+            Color::Black => -8,
+        }
+    }
+
+    /// Returns the rank on which pawns of this color promote.
+    #[must_use]
+    pub fn promotion_rank(self) -> Bitboard {
+        match self {
+            Color::White => RANK8,
+            Color::Black => RANK1,
+        }
+    }
+
+    /// Returns the rank on which the pieces of this color start the game.
+    #[must_use]
+    pub fn back_rank(self) -> Bitboard {
+        match self {
+            Color::White => RANK1,
+            Color::Black => RANK8,
+        }
+    }
+
+    /// Returns the rank of the en passant target square left behind by a double push
+    /// from a pawn of this color.
+    #[must_use]
+    pub fn en_passant_rank(self) -> Bitboard {
+        match self {
+            Color::White => RANK3,
+            Color::Black => RANK6,
+        }
+    }
 }
 
 impl Not for Color {
@@ -171,6 +300,33 @@ mod tests {
         assert_eq!(a[PieceType::King], 11);
     }
 
+    #[test]
+    fn from_idx_inverts_idx() {
+        for piece in PieceType::iterator() {
+            assert_eq!(PieceType::from_idx(piece.idx()), Some(*piece));
+        }
+        assert_eq!(PieceType::King.idx(), 0);
+        assert_eq!(PieceType::Pawn.idx(), 5);
+        assert_eq!(PieceType::from_idx(PIECE_COUNT), None);
+    }
+
+    #[test]
+    fn only_non_pawn_material_counts_towards_phase() {
+        let start: isize = [
+            (PieceType::Knight, 4),
+            (PieceType::Bishop, 4),
+            (PieceType::Rook, 4),
+        ]
+        .iter()
+        .map(|(piece, count)| piece.phase() * count)
+        .sum::<isize>()
+            + PieceType::Queen.phase() * 2;
+        assert_eq!(start, 24);
+        assert_eq!(PieceType::Pawn.phase(), 0);
+        assert_eq!(PieceType::King.phase(), 0);
+        assert!(PieceType::Rook.endgame_value() > PieceType::Rook.material_value());
+    }
+
     #[test]
     fn can_index_using_colors() {
         let mut a: [Bitboard; COLOR_COUNT] = [0; COLOR_COUNT];
@@ -188,6 +344,30 @@ mod tests {
         assert_eq!(!black, Color::White);
     }
 
+    #[test]
+    fn correct_pawn_push_offset() {
+        assert_eq!(Color::White.pawn_push_offset(), 8);
+        assert_eq!(Color::Black.pawn_push_offset(), -8);
+    }
+
+    #[test]
+    fn correct_promotion_rank() {
+        assert_eq!(Color::White.promotion_rank(), RANK8);
+        assert_eq!(Color::Black.promotion_rank(), RANK1);
+    }
+
+    #[test]
+    fn correct_back_rank() {
+        assert_eq!(Color::White.back_rank(), RANK1);
+        assert_eq!(Color::Black.back_rank(), RANK8);
+    }
+
+    #[test]
+    fn correct_en_passant_rank() {
+        assert_eq!(Color::White.en_passant_rank(), RANK3);
+        assert_eq!(Color::Black.en_passant_rank(), RANK6);
+    }
+
     #[test]
     #[should_panic]
     fn should_panic() {
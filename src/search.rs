@@ -1,5 +1,33 @@
-pub mod alpha_beta;
-pub mod eval;
-pub mod minimax;
-pub mod search;
-pub mod stats;
+//! Searchers which pick the best move in a position, for clients who want to drive and configure
+//! a search directly rather than going through `Game`.
+//!
+//! ```rust
+//! use purple::search::{parse_fen, AlphaBeta, Searcher};
+//!
+//! let mut pos = parse_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+//! let mut searcher = AlphaBeta::new();
+//! searcher.use_table(false);
+//! searcher.move_time(100);
+//!
+//! let best_move = searcher.best_move_depth(&mut pos, 3);
+//! assert_eq!(best_move.mv.to_algebraic(), "d2d5");
+//! println!("searched {} nodes", searcher.stats().nodes);
+//! ```
+
+pub(crate) mod alpha_beta;
+pub(crate) mod compare;
+pub(crate) mod eval;
+pub(crate) mod minimax;
+pub(crate) mod search;
+pub(crate) mod stats;
+pub(crate) mod tablebase;
+
+pub use self::alpha_beta::AlphaBeta;
+pub use self::compare::{compare_searchers, Disagreement};
+pub use self::eval::{DefaultEval, Eval};
+pub use self::search::{SearchResult, Searcher};
+pub use self::stats::Stats;
+pub use self::tablebase::{NoTablebase, Tablebase, Wdl};
+pub use crate::board::BoardState;
+pub use crate::chess_move::{EvaledMove, Move};
+pub use crate::fen::parse_fen;
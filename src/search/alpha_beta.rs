@@ -1,60 +1,109 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 use itertools::Itertools;
 
 use super::eval::MATE_VALUE;
-use super::search::Searcher;
+use super::search::{SearchResult, Searcher};
 use crate::board::BoardState;
-use crate::chess_move::{self, EvaledMove, Move, MoveType};
+use crate::chess_move::{EvaledMove, Move, MoveType};
 use crate::move_gen::{king_square, MoveGenerator};
-use crate::search::eval::{eval, INF, NEG_INF};
+use crate::piece::PieceType;
+use crate::search::eval::{DefaultEval, Eval, INF, NEG_INF};
 use crate::search::stats::Stats;
-use crate::table::{Bound, Entry, TranspositionTable, ZobristTable};
+use crate::search::tablebase::{NoTablebase, Tablebase};
+use crate::table::{
+    Bound, Entry, EvalCache, TranspositionTable, ZobristHash, ZobristTable, ZOBRIST_SEED,
+};
+
+/// The maximum number of plies searched by the quiescence search.
+const Q_SEARCH_DEPTH: usize = 5;
+
+/// The deepest remaining depth at which reverse futility pruning is tried.
+const REVERSE_FUTILITY_DEPTH: u8 = 3;
+
+/// The minimum remaining depth at which null move cutoffs are verified by default. Shallower
+/// cutoffs are cheap to get wrong, but verifying them would cost a search at nearly every node.
+const NMP_VERIFY_DEPTH: u8 = 5;
+
+/// The size in megabytes of the transposition table of a new searcher.
+const DEFAULT_TABLE_MB: usize = 50;
+
+/// The number of static evaluations held by the evaluation cache, which takes up 1 MB.
+const EVAL_CACHE_ENTRIES: usize = 1 << 16;
+
+/// The minimum time in milliseconds between the `info` lines reporting progress within an
+/// iteration, so that GUIs keep updating their node counters during long iterations.
+const PROGRESS_INTERVAL_MS: u128 = 1000;
+
+/// The number of nodes between checks of the clock for a progress report, so that the clock is
+/// not read at every node.
+const PROGRESS_CHECK_NODES: usize = 1024;
+
+/// The number of times a failed aspiration window is doubled before falling back to a full window.
+const ASPIRATION_WIDENINGS: usize = 2;
+
+/// The number of moves tried at a node without raising alpha after which its remaining quiet moves
+/// are pruned, for each remaining depth from one ply upwards. Deeper nodes are never pruned this
+/// way.
+const LATE_MOVE_COUNTS: [usize; 3] = [8, 12, 18];
 
 #[allow(clippy::struct_excessive_bools)]
 pub struct Settings {
     use_table: bool,
+    clear_between_moves: bool,
     use_idd: bool,
     use_move_ordering: bool,
     use_lmr: bool,
     use_fp: bool,
+    late_move_counts: Vec<usize>,
+    futility_margin: isize,
+    reverse_futility_margin: isize,
+    use_nmp: bool,
+    use_root_ordering: bool,
+    nmp_reduction: u8,
+    nmp_min_depth: u8,
+    nmp_material_guard: bool,
+    nmp_verify_depth: Option<u8>,
+    q_check_plies: usize,
+    aspiration_delta: Option<isize>,
     move_time: Option<u128>,
+    node_limit: Option<usize>,
+    mate_moves: Option<usize>,
+    debug: bool,
+    progress_info: bool,
+    progress_interval: u128,
 }
 
 pub struct AlphaBeta {
-    pub gen: MoveGenerator,
+    pub gen: Arc<MoveGenerator>,
     stats: Stats,
     zobrist: ZobristTable,
     table: TranspositionTable,
+    eval_cache: EvalCache,
     settings: Settings,
+    evaluator: Arc<dyn Eval>,
+    tablebase: Arc<dyn Tablebase>,
     start_time: Instant,
     cutoff: isize,
+    stop: Arc<AtomicBool>,
+    in_null_move: bool,
+    completed_depth: usize,
+    nodes_searched: usize,
+    researches: usize,
+    last_progress: u128,
+    progress_checks: usize,
+    progress_reports: usize,
+    root_best: Option<Move>,
+    line: Vec<(ZobristHash, usize)>,
+    repetitions: usize,
 }
 
 impl Searcher for AlphaBeta {
     fn new() -> Self {
-        let gen = MoveGenerator::new();
-        let stats = Stats::new();
-        let zobrist = ZobristTable::init();
-        let table = TranspositionTable::new_mb(50);
-        let settings = Settings {
-            use_table: true,
-            use_idd: true,
-            use_move_ordering: true,
-            use_lmr: true,
-            use_fp: true,
-            move_time: None,
-        };
-        let start_time = Instant::now();
-        AlphaBeta {
-            gen,
-            stats,
-            zobrist,
-            table,
-            settings,
-            start_time,
-            cutoff: 0,
-        }
+        AlphaBeta::with_table_mb(DEFAULT_TABLE_MB)
     }
 
     fn stats(&self) -> &Stats {
@@ -66,28 +115,51 @@ impl Searcher for AlphaBeta {
         self.best_move_depth(pos, 6)
     }
 
-    /// Performs an iterative deepening search until the specified depth and returns the best move
+    /// Performs an iterative deepening search until the specified depth and returns the best move.
+    ///
+    /// The transposition table is kept from one search to the next unless `clear_between_moves`
+    /// is set, so that the next move of a game starts from the entries of the previous search.
     fn best_move_depth(&mut self, pos: &mut BoardState, depth: usize) -> EvaledMove {
         self.start_time = Instant::now();
+        if self.settings.clear_between_moves {
+            self.table.clear();
+        }
 
         let mut best_move: EvaledMove = EvaledMove::null(0);
-        let mut j = 0;
+        self.completed_depth = 0;
+        self.nodes_searched = 0;
+        self.researches = 0;
+        self.last_progress = 0;
+        self.progress_checks = 0;
+        self.progress_reports = 0;
+        self.stats.reset();
+        self.root_best = None;
+        self.line.clear();
+        self.repetitions = 0;
         for i in 0..=depth {
             //loop {
             if self.time_expired() {
                 break;
             }
 
-            let next = self.alpha_beta(pos, NEG_INF, INF, i as u8, 0);
+            self.nodes_searched += self.stats.nodes;
+            self.stats.reset();
+            let next = self.aspiration_search(pos, best_move.eval, i as u8);
             if next.is_none() {
                 break;
             }
             best_move = next.unwrap();
-            j += 1;
-            println!("depth: {}, nodes: {}", j, self.stats.nodes);
-            println!("  cutoff: {}, nodes: {}", j, self.cutoff);
+            self.completed_depth = i;
+            if best_move.mv.kind != MoveType::Null {
+                self.root_best = Some(best_move.mv);
+            }
+            if i > 0 {
+                self.print_info(pos, best_move, i);
+            }
             self.cutoff = 0;
-            self.stats.reset();
+            if i > 0 && self.found_mate(best_move.eval, i) {
+                break;
+            }
         }
         //let pv = self.table.pv(pos, &self.zobrist);
         //println!("PV: {:?}", pv);
@@ -98,6 +170,48 @@ impl Searcher for AlphaBeta {
     fn move_time(&mut self, miliseconds: u128) {
         self.settings.move_time = Some(miliseconds);
     }
+
+    /// Performs an iterative deepening search until the specified depth, and reads the principal
+    /// variation back from the transposition table.
+    fn search(&mut self, pos: &mut BoardState, depth: usize) -> SearchResult {
+        let best = self.best_move_depth(pos, depth);
+        let mut pv = self
+            .table
+            .pv(pos, &self.zobrist)
+            .iter()
+            .map(|e| e.mv)
+            .collect_vec();
+        // The entry for the root may have been replaced since it was searched.
+        if pv.first() != Some(&best.mv) {
+            pv = Some(best.mv)
+                .filter(|mv| mv.kind != MoveType::Null)
+                .into_iter()
+                .collect();
+        }
+
+        SearchResult {
+            best_move: best.mv,
+            score: best.eval,
+            depth: self.completed_depth,
+            nodes: self.nodes_searched() as u64,
+            pv,
+        }
+    }
+}
+
+/// Return the number of moves until mate for a score found by an iteration to the given depth,
+/// which is positive if the player to move delivers mate and negative if they are mated, or
+/// `None` if the score is not a mate. Mate scores count the depth remaining when mate is reached,
+/// so the number of plies to mate is the depth searched minus that remainder. Mates found in
+/// quiescence are scored as if at the horizon and their distance is unknown, so they give `None`.
+fn mate_distance(eval: isize, depth: usize) -> Option<isize> {
+    if eval.abs() <= MATE_VALUE {
+        return None;
+    }
+    let remaining = eval.abs() - MATE_VALUE;
+    let plies = (isize::try_from(depth).unwrap_or(isize::MAX) - remaining).max(1);
+    let moves = (plies + 1) / 2;
+    Some(if eval > 0 { moves } else { -moves })
 }
 
 /// Given an entry to save and values for alpha/beta in a negamax implementation, returns whether
@@ -123,10 +237,118 @@ fn leaf_bound(best_move: EvaledMove, alpha: isize, beta: isize) -> Bound {
 }
 
 impl AlphaBeta {
+    /// Construct a searcher with a transposition table of the given size in megabytes. Small
+    /// tables are cheaper to create, which suits short searches such as hints.
+    #[must_use]
+    pub fn with_table_mb(size: usize) -> AlphaBeta {
+        AlphaBeta::with_generator(Arc::new(MoveGenerator::new()), size)
+    }
+
+    /// Construct a searcher with its own transposition table of the given size in megabytes, which
+    /// shares the move generator, static evaluation, and tablebase of this one. None of the search
+    /// state or settings are shared, so searching with it leaves this searcher as it was.
+    #[must_use]
+    pub fn fork_with_table_mb(&self, size: usize) -> AlphaBeta {
+        let mut searcher = AlphaBeta::with_generator(Arc::clone(&self.gen), size);
+        searcher.evaluator = Arc::clone(&self.evaluator);
+        searcher.tablebase = Arc::clone(&self.tablebase);
+        searcher
+    }
+
+    fn with_generator(gen: Arc<MoveGenerator>, size: usize) -> AlphaBeta {
+        let stats = Stats::new();
+        let zobrist = ZobristTable::from_seed(ZOBRIST_SEED);
+        let table = TranspositionTable::new_mb(size);
+        let settings = Settings {
+            use_table: true,
+            clear_between_moves: false,
+            use_idd: true,
+            use_move_ordering: true,
+            use_lmr: true,
+            use_fp: true,
+            late_move_counts: LATE_MOVE_COUNTS.to_vec(),
+            futility_margin: 200,
+            reverse_futility_margin: 150,
+            use_nmp: true,
+            use_root_ordering: true,
+            nmp_reduction: 2,
+            nmp_min_depth: 3,
+            nmp_material_guard: true,
+            nmp_verify_depth: Some(NMP_VERIFY_DEPTH),
+            q_check_plies: 1,
+            aspiration_delta: Some(50),
+            move_time: None,
+            node_limit: None,
+            mate_moves: None,
+            debug: false,
+            progress_info: false,
+            progress_interval: PROGRESS_INTERVAL_MS,
+        };
+        let start_time = Instant::now();
+        AlphaBeta {
+            gen,
+            stats,
+            zobrist,
+            table,
+            eval_cache: EvalCache::new(EVAL_CACHE_ENTRIES),
+            settings,
+            evaluator: Arc::new(DefaultEval),
+            tablebase: Arc::new(NoTablebase),
+            start_time,
+            cutoff: 0,
+            stop: Arc::new(AtomicBool::new(false)),
+            in_null_move: false,
+            completed_depth: 0,
+            nodes_searched: 0,
+            researches: 0,
+            last_progress: 0,
+            progress_checks: 0,
+            progress_reports: 0,
+            root_best: None,
+            line: Vec::new(),
+            repetitions: 0,
+        }
+    }
+
+    /// Search the root in a window of `aspiration_delta` around the score of the previous
+    /// iteration. Whenever the score falls outside of the window the search is repeated with the
+    /// window doubled, until after `ASPIRATION_WIDENINGS` doublings the full window is used.
+    /// The first two iterations and mate scores always use the full window, since their scores
+    /// change too much between iterations.
+    fn aspiration_search(
+        &mut self,
+        pos: &mut BoardState,
+        prev_eval: isize,
+        depth: u8,
+    ) -> Option<EvaledMove> {
+        let mut delta = self
+            .settings
+            .aspiration_delta
+            .filter(|_| depth > 1 && prev_eval.abs() < MATE_VALUE);
+        let mut widenings = 0;
+        loop {
+            let (alpha, beta) = match delta {
+                Some(d) => (prev_eval - d, prev_eval + d),
+                None => (NEG_INF, INF),
+            };
+
+            let result = self.alpha_beta(pos, alpha, beta, depth, 0)?;
+            if delta.is_none() || (alpha < result.eval && result.eval < beta) {
+                return Some(result);
+            }
+
+            self.researches += 1;
+            delta = delta
+                .filter(|_| widenings < ASPIRATION_WIDENINGS)
+                .map(|d| d * 2);
+            widenings += 1;
+        }
+    }
+
     fn alpha_beta(
         &mut self,
         pos: &mut BoardState,
-        mut alpha: isize,
+        alpha: isize,
         beta: isize,
         depth: u8,
         ply: u8,
@@ -135,95 +357,109 @@ impl AlphaBeta {
         if self.time_expired() {
             return None;
         }
+        self.report_progress();
+
+        // A position which already occurred earlier in the current line can be repeated again and
+        // again, so the first repetition is scored as a draw. Each position on the line keeps the
+        // number of repetitions scored before it was reached, see `save`.
+        let hash = self.zobrist.hash(pos);
+        if ply > 0 && self.line.iter().any(|(h, _)| *h == hash) {
+            self.repetitions += 1;
+            return Some(EvaledMove::null(0));
+        }
+
+        self.line.push((hash, self.repetitions));
+        let result = self.search_node(pos, alpha, beta, depth, ply, hash);
+        self.line.pop();
+        result
+    }
 
-        if let Some(e) = self.table_fetch(pos, alpha, beta, depth) {
+    /// Search a position which has not yet occurred in the current line, see `alpha_beta`.
+    fn search_node(
+        &mut self,
+        pos: &mut BoardState,
+        mut alpha: isize,
+        beta: isize,
+        depth: u8,
+        ply: u8,
+        hash: ZobristHash,
+    ) -> Option<EvaledMove> {
+        let (cutoff, hint) = self.probe(hash, alpha, beta, depth);
+        if let Some(e) = cutoff.or_else(|| self.probe_tablebase(pos, ply)) {
             return Some(e);
         }
 
         let prev_alpha = alpha;
         let mut best_move = EvaledMove::null(alpha);
-        let mut moves = Vec::<EvaledMove>::new();
-
-        let hash = self.zobrist.hash(pos);
-        if let Some(e) = self.table.get(hash) {
-            if self.settings.use_table {
-                if e.hash == hash && e.depth >= depth as u8 && is_bound_ok(&e, alpha, beta) {
-                    return Some(e.best_move);
-                }
-
-                if e.hash == hash && e.best_move.mv.kind != MoveType::Null {
-                    moves.push(e.best_move);
-                }
-            }
-        }
+        let mut moves = self.first_moves(hint, ply);
 
         if depth == 0 {
-            let s = EvaledMove::null(self.q_search(pos, alpha, beta, 5));
+            let s = EvaledMove::null(self.q_search(pos, hash, alpha, beta, Q_SEARCH_DEPTH)?);
             let bound = leaf_bound(s, alpha, beta);
             self.save(pos, s, bound, depth as u8);
             return Some(s);
         }
 
-        let is_leftmost_node = if ply % 2 == 0 {
-            alpha == NEG_INF && beta == INF
-        } else {
-            alpha == INF && beta == NEG_INF
-        };
+        // Nodes on the principal variation are searched with an open window, and every other node
+        // with a null window. Testing for the full window instead would miss the principal
+        // variation whenever the root is searched with an aspiration window.
+        let is_leftmost_node = beta - alpha > 1;
+
+        if !is_leftmost_node && ply > 0 {
+            if let Some(e) = self.reverse_futility(pos, hash, beta, depth) {
+                return Some(e);
+            }
+            if let Some(e) = self.null_move_search(pos, hash, beta, depth, ply) {
+                return Some(e);
+            }
+        }
 
         // If we haven't found a best move to search first yet, and we are on a left-most node,
-        // then perform an IID search to determine the best node to search first
+        // then perform an IID search to determine the best node to search first. The position is
+        // already on the line, so it is searched directly rather than scored as a repetition.
         let can_perform_iid =
             moves.is_empty() && depth > 3 && is_leftmost_node && self.settings.use_idd;
         if can_perform_iid {
-            if let Some(e) = self.alpha_beta(pos, alpha, beta, depth / 2, ply + 1) {
+            if let Some(e) = self.search_node(pos, alpha, beta, depth / 2, ply, hash) {
                 moves.push(e);
             }
         }
 
-        let mut gen = evaled_moves(&self.gen.all_moves(pos));
-        self.sort_moves(&mut gen, pos);
-        moves.append(&mut gen);
+        self.append_legal_moves(pos, &mut moves);
 
         if moves.is_empty() {
             return Some(self.no_move_eval(pos, depth as usize));
         }
 
+        // Quiet moves are only pruned at nodes which are off the principal variation and not in
+        // check, and late moves only until alpha is raised.
+        let can_prune = !is_leftmost_node && ply > 0 && !self.gen.is_in_check(pos);
+        let frontier_eval = self.frontier_eval(pos, hash, depth, can_prune);
+
         let mut is_first_move = true;
-        for mv in &mut moves {
+        for (i, mv) in moves.iter_mut().enumerate() {
             let mut new_pos = pos.clone_with_move(mv.mv);
 
+            // Captures, promotions and checks are never pruned.
+            let is_prunable = (self.is_futile(frontier_eval, alpha)
+                || (can_prune && alpha == prev_alpha && self.is_late_move(i, depth)))
+                && !mv.mv.is_capture()
+                && !mv.mv.is_promotion();
+            if is_prunable && !self.gen.is_in_check(&new_pos) {
+                continue;
+            }
+
             let next = if is_first_move {
                 is_first_move = false;
                 self.alpha_beta(&mut new_pos, -beta, -alpha, depth - 1, ply + 1)
             } else {
-                let in_check = self.gen.is_in_check(&new_pos);
-                let is_giving_check = self.gen.is_giving_check(&mut new_pos);
-                let can_futility_prune = !is_leftmost_node
-                    && depth < 6
-                    && !in_check
-                    && !is_giving_check
-                    && !mv.mv.is_capture()
-                    && !mv.mv.is_promotion()
-                    && !mv.mv.is_promotion_capture()
-                    && self.settings.use_fp;
-
-                if can_futility_prune {
-                    let margin = 500 * depth as isize;
-                    let static_eval = eval(&new_pos);
-                    if static_eval + margin < alpha {
-                        continue;
-                    }
-                }
-
                 self.lmr_search(&mut new_pos, mv, alpha, beta, depth, ply)
             };
 
+            // Moves whose search was cut short are not counted, so that a node limit is not
+            // overshot while the search unwinds.
+            mv.eval = -next?.eval;
             self.stats.count_node();
-            if next.is_none() {
-                return next;
-            }
-
-            mv.eval = -next.unwrap().eval;
             if mv.eval > alpha {
                 alpha = mv.eval;
                 best_move = *mv;
@@ -245,6 +481,169 @@ impl AlphaBeta {
         Some(best_move)
     }
 
+    /// Return the moves to search before the rest of the legal moves at a node, given the move
+    /// hinted by the table. At the root, the best move of the previous iteration is searched
+    /// first, even if its entry in the table has since been overwritten.
+    fn first_moves(&self, hint: Option<Move>, ply: u8) -> Vec<EvaledMove> {
+        let mut moves = Vec::<EvaledMove>::new();
+        if ply == 0 && self.settings.use_root_ordering {
+            if let Some(mv) = self.root_best.filter(|mv| hint != Some(*mv)) {
+                moves.push(EvaledMove { mv, eval: 0 });
+            }
+        }
+        if let Some(mv) = hint {
+            moves.push(EvaledMove { mv, eval: 0 });
+        }
+        moves
+    }
+
+    /// Return the score of a position below the root which is held by the tablebase.
+    fn probe_tablebase(&self, pos: &BoardState, ply: u8) -> Option<EvaledMove> {
+        if ply == 0 || pos.bb_all().count_ones() > self.tablebase.max_pieces() {
+            return None;
+        }
+        let wdl = self.tablebase.probe_wdl(pos)?;
+        Some(EvaledMove::null(wdl.score(ply)))
+    }
+
+    /// Return the static evaluation of the given position with the given hash, from the
+    /// evaluation cache if it has been evaluated before.
+    fn static_eval(&mut self, pos: &BoardState, hash: ZobristHash) -> isize {
+        if let Some(eval) = self.eval_cache.get(hash) {
+            return eval;
+        }
+        let eval = self.evaluator.evaluate(pos);
+        self.eval_cache.save(hash, eval);
+        eval
+    }
+
+    /// Return the static evaluation of a frontier node, one ply above the leaves, at which quiet
+    /// moves may be pruned, which the caller allows only off the principal variation and when not
+    /// in check.
+    fn frontier_eval(
+        &mut self,
+        pos: &BoardState,
+        hash: ZobristHash,
+        depth: u8,
+        can_prune: bool,
+    ) -> Option<isize> {
+        if can_prune && depth == 1 && self.settings.use_fp {
+            Some(self.static_eval(pos, hash))
+        } else {
+            None
+        }
+    }
+
+    /// Quiet moves at frontier nodes are unlikely to change the static evaluation by more than
+    /// the futility margin, so when even that is not enough to raise alpha they are skipped.
+    fn is_futile(&self, frontier_eval: Option<isize>, alpha: isize) -> bool {
+        frontier_eval.is_some_and(|e| e.saturating_add(self.settings.futility_margin) <= alpha)
+    }
+
+    /// Late move pruning: moves are ordered from most to least promising, so once the first few
+    /// moves of a shallow node have all failed to raise alpha, the remaining quiet moves are
+    /// assumed to fail low as well and are skipped. How many moves are searched first depends on
+    /// the remaining depth, see `late_move_pruning`.
+    fn is_late_move(&self, index: usize, depth: u8) -> bool {
+        self.settings
+            .late_move_counts
+            .get(usize::from(depth) - 1)
+            .is_some_and(|count| index >= *count)
+    }
+
+    /// Also known as static null move pruning. When the static evaluation of a shallow node is
+    /// better than beta by more than `reverse_futility_margin` per remaining ply, the opponent is
+    /// assumed to have a better alternative earlier on, and the static evaluation is returned
+    /// without searching. Nodes in check are never pruned, since their static evaluation cannot
+    /// be trusted, and neither are principal variation nodes, which the caller excludes.
+    fn reverse_futility(
+        &mut self,
+        pos: &mut BoardState,
+        hash: ZobristHash,
+        beta: isize,
+        depth: u8,
+    ) -> Option<EvaledMove> {
+        if depth > REVERSE_FUTILITY_DEPTH || beta.abs() >= MATE_VALUE || self.gen.is_in_check(pos) {
+            return None;
+        }
+
+        let static_eval = self.static_eval(pos, hash);
+        let margin = self
+            .settings
+            .reverse_futility_margin
+            .saturating_mul(isize::from(depth));
+        if static_eval.saturating_sub(margin) >= beta {
+            Some(EvaledMove::null(static_eval))
+        } else {
+            None
+        }
+    }
+
+    /// Try to prove that the position is already good enough to fail high by letting the opponent
+    /// move twice in a row, searching the result at a depth reduced by `nmp_reduction`. Returns
+    /// the cutoff value if the null move search (and the verification search, if enabled) failed
+    /// high. If time expires nothing is returned, and the expiry is picked up by the caller's next
+    /// search.
+    ///
+    /// Null moves are skipped when in check, and by default when the side to move only has pawns
+    /// left, since those positions are prone to zugzwang where passing is better than any legal
+    /// move, see `null_move_material_guard`. The verification search guards against the remaining
+    /// zugzwang positions by confirming cutoffs at a remaining depth of at least `nmp_verify_depth`
+    /// with a regular search one ply shallower, in which no further null moves are tried.
+    fn null_move_search(
+        &mut self,
+        pos: &mut BoardState,
+        hash: ZobristHash,
+        beta: isize,
+        depth: u8,
+        ply: u8,
+    ) -> Option<EvaledMove> {
+        let can_null_move = self.settings.use_nmp
+            && !self.in_null_move
+            && depth >= self.settings.nmp_min_depth
+            && beta.abs() < MATE_VALUE
+            && (!self.settings.nmp_material_guard || pos.side_to_move_has_non_pawn_material())
+            && !self.gen.is_in_check(pos);
+        if !can_null_move {
+            return None;
+        }
+
+        let reduced_depth = depth.saturating_sub(self.settings.nmp_reduction + 1);
+
+        let mut null_pos = *pos;
+        null_pos.en_passant = None;
+        null_pos.switch();
+
+        self.in_null_move = true;
+        let next = self.alpha_beta(&mut null_pos, -beta, -beta + 1, reduced_depth, ply + 1);
+        self.in_null_move = false;
+        if -next?.eval < beta {
+            return None;
+        }
+
+        // As the position is already on the line, it is searched directly rather than scored as a
+        // repetition.
+        if self
+            .settings
+            .nmp_verify_depth
+            .is_some_and(|verify_depth| depth >= verify_depth)
+        {
+            self.in_null_move = true;
+            let verified = self.search_node(pos, beta - 1, beta, depth - 1, ply, hash);
+            self.in_null_move = false;
+            if verified?.eval < beta {
+                return None;
+            }
+        }
+
+        Some(EvaledMove::null(beta))
+    }
+
+    /// Search a move which was not the first to be searched in its node. Quiet moves may be
+    /// searched at a reduced depth with a null window, and any reduced move which beats alpha is
+    /// verified at full depth before it is accepted. Moves at the root are never reduced, since a
+    /// quiet move there which only pays off deeper in the tree would otherwise never get the chance
+    /// to beat alpha.
     fn lmr_search(
         &mut self,
         pos: &mut BoardState,
@@ -254,16 +653,16 @@ impl AlphaBeta {
         depth: u8,
         ply: u8,
     ) -> Option<EvaledMove> {
-        let is_leftmost_node = if ply % 2 == 0 {
-            alpha == NEG_INF && beta == INF
-        } else {
-            alpha == INF && beta == NEG_INF
-        };
+        // Nodes on the principal variation are searched with an open window, and every other node
+        // with a null window. Testing for the full window instead would miss the principal
+        // variation whenever the root is searched with an aspiration window.
+        let is_leftmost_node = beta - alpha > 1;
 
         let in_check = self.gen.is_in_check(pos);
         let mut r = 0;
 
         let can_late_move_reduce = !is_leftmost_node
+            && ply > 0
             && !in_check
             && !mv.mv.is_capture()
             && !mv.mv.is_promotion()
@@ -297,57 +696,72 @@ impl AlphaBeta {
 
     /// Perform a Quiescence search, which evaluates up to a certain provided maximum depth
     /// or until a position reaches a "quiet" state (i.e., one in which there are no captures).
+    /// In the first `q_check_plies` plies, quiet moves which give check are searched as well, so
+    /// that tactics starting with a quiet check (such as a forking knight check) are not missed.
+    /// Like `alpha_beta`, returns `None` if time expires, so that no partial score is ever used.
+    /// The hash of each position is updated from its parent's rather than computed from scratch,
+    /// and is only used to look up its static evaluation in the evaluation cache.
     fn q_search(
         &mut self,
         pos: &mut BoardState,
+        hash: ZobristHash,
         mut alpha: isize,
         beta: isize,
         depth: usize,
-    ) -> isize {
-        let eval = eval(pos);
-
+    ) -> Option<isize> {
         if self.time_expired() {
-            return eval;
+            return None;
         }
 
+        let eval = self.static_eval(pos, hash);
         if depth == 0 {
-            return eval;
+            return Some(eval);
         }
 
-        if eval >= beta {
-            return beta;
-        } else if eval > alpha {
-            alpha = eval;
-        };
-
+        // Standing pat is not an option when in check, otherwise any quiet check searched below
+        // would be answered by the static evaluation of the checked side.
         let is_attacked = self.gen.is_attacked(pos, king_square(pos));
+        if !is_attacked {
+            if eval >= beta {
+                return Some(beta);
+            } else if eval > alpha {
+                alpha = eval;
+            };
+        }
 
+        let include_checks = Q_SEARCH_DEPTH - depth < self.settings.q_check_plies;
         let mut moves = if is_attacked {
             self.gen.all_moves(pos)
         } else {
             self.gen
                 .all_moves(pos)
                 .into_iter()
-                .filter(chess_move::Move::is_capture)
+                .filter(|mv| {
+                    mv.is_capture()
+                        || (include_checks && self.gen.is_in_check(&pos.clone_with_move(*mv)))
+                })
                 .collect()
         };
 
         if moves.is_empty() && is_attacked {
-            return self.no_move_eval(pos, depth).eval;
+            // Mates found in quiescence are scored as if found at the horizon, so that they are
+            // never preferred over a faster mate found by the main search.
+            return Some(self.no_move_eval(pos, 0).eval);
         }
 
         for mv in &mut moves {
             let mut new_pos = pos.clone_with_move(*mv);
-            let eval = -self.q_search(&mut new_pos, -beta, -alpha, depth - 1);
+            let new_hash = self.zobrist.hash_after(hash, pos, &new_pos);
+            let eval = -self.q_search(&mut new_pos, new_hash, -beta, -alpha, depth - 1)?;
             if eval >= beta {
-                return beta;
+                return Some(beta);
             }
 
             if eval > alpha {
                 alpha = eval;
             }
         }
-        alpha
+        Some(alpha)
     }
 
     /// Return an evaluation of the given position, at the given depth, assuming there are no valid
@@ -363,36 +777,54 @@ impl AlphaBeta {
         }
     }
 
-    /// Given a position, alpha/beta, and a depth from the bottom of the tree, attempts to fetch the
-    /// evaluated move from the transposition table. Only entries with valid bounds and depths will
-    /// be returned.
-    pub fn table_fetch(
+    /// Given a hash, alpha/beta, and a depth from the bottom of the tree, performs a single lookup
+    /// in the transposition table. The first value returned is the evaluated move to use as a
+    /// cutoff, which is only present for entries with valid bounds and depths. The second value is
+    /// the best move previously found for this position, which should be searched first.
+    #[must_use]
+    pub fn probe(
         &self,
-        pos: &mut BoardState,
+        hash: ZobristHash,
         alpha: isize,
         beta: isize,
         depth: u8,
-    ) -> Option<EvaledMove> {
+    ) -> (Option<EvaledMove>, Option<Move>) {
         if !self.settings.use_table {
-            return None;
+            return (None, None);
         }
 
-        let hash = self.zobrist.hash(pos);
-        let entry = self.table.get(hash);
-        entry?;
-        let entry = entry.unwrap();
-        if entry.hash == hash && entry.depth >= depth && is_bound_ok(&entry, alpha, beta) {
-            Some(entry.best_move)
-        } else {
-            None
+        match self.table.get(hash) {
+            Some(entry) if entry.hash == hash => {
+                let cutoff = if entry.depth >= depth && is_bound_ok(&entry, alpha, beta) {
+                    Some(entry.best_move)
+                } else {
+                    None
+                };
+                let hint = if entry.best_move.mv.kind == MoveType::Null {
+                    None
+                } else {
+                    Some(entry.best_move.mv)
+                };
+                (cutoff, hint)
+            }
+            _ => (None, None),
         }
     }
 
-    /// Saves the given entry in the transposition table.
+    /// Saves the given entry in the transposition table. A draw by repetition depends on the moves
+    /// which led to the position, so a draw score is not saved if a repetition was scored below
+    /// the current node, or it would be reused when the position is reached by another line.
     fn save(&mut self, pos: &mut BoardState, best_move: EvaledMove, bound: Bound, depth: u8) {
         if !self.settings.use_table {
             return;
         }
+        let after_repetition = self
+            .line
+            .last()
+            .is_some_and(|(_, repetitions)| *repetitions != self.repetitions);
+        if best_move.eval == 0 && after_repetition {
+            return;
+        }
 
         let hash = self.zobrist.hash(pos);
         //let fen = debug_print(pos);
@@ -405,75 +837,456 @@ impl AlphaBeta {
         self.table.save(hash, entry);
     }
 
-    /// Set whether or not the searcher should use a transposition table to lookup previous evaluations.
-    #[allow(dead_code)]
-    pub fn use_table(&mut self, setting: bool) {
-        self.settings.use_table = setting;
+    /// Set whether or not each iteration of the search reports extra `info string` diagnostics
+    /// alongside its standard `info` line, as requested by the UCI `debug` command.
+    pub fn debug(&mut self, setting: bool) {
+        self.settings.debug = setting;
     }
 
-    fn time_expired(&self) -> bool {
-        if self.settings.move_time.is_none() {
-            return false;
-        }
+    /// Return whether or not debug diagnostics are reported, as set by `debug`.
+    #[must_use]
+    pub fn is_debug(&self) -> bool {
+        self.settings.debug
+    }
 
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.start_time).as_millis();
-        self.settings.move_time.unwrap() < elapsed
+    /// Set whether or not `info` lines with the nodes searched so far are printed during long
+    /// iterations, as UCI GUIs expect. They are always printed when debugging.
+    pub fn progress_info(&mut self, setting: bool) {
+        self.settings.progress_info = setting;
     }
 
-    fn sort_moves(&self, moves: &mut [EvaledMove], pos: &BoardState) {
-        if !self.settings.use_move_ordering {
-            return;
-        }
+    /// Set the minimum time in milliseconds between progress `info` lines, which is
+    /// `PROGRESS_INTERVAL_MS` by default.
+    #[allow(dead_code)]
+    pub fn progress_interval(&mut self, ms: u128) {
+        self.settings.progress_interval = ms;
+    }
 
-        moves.sort_by_cached_key(|mv: &EvaledMove| {
-            let maybe_capturing_piece = pos.type_on(mv.mv.from).unwrap();
-            if mv.mv.is_en_passant_capture() {
-                return 0;
-            }
+    /// Set the static evaluation used by the search in place of the built-in `eval`. Entries
+    /// already in the transposition table were scored by the previous evaluation, so the table
+    /// should be cleared if the two are not meant to be mixed. Cached static evaluations are
+    /// always cleared.
+    #[allow(dead_code)]
+    pub fn set_eval(&mut self, evaluator: Box<dyn Eval>) {
+        self.evaluator = Arc::from(evaluator);
+        self.eval_cache.clear();
+    }
 
-            if mv.mv.is_capture() {
-                let captured_piece = pos.type_on(mv.mv.to).unwrap();
-                return MVV_LVA[captured_piece.idx()][maybe_capturing_piece.idx()] - 100;
-            }
+    /// Set the endgame tablebase consulted by the search, which replaces the search of any position
+    /// it holds by the score of its result.
+    #[allow(dead_code)]
+    pub fn set_tablebase(&mut self, tablebase: Box<dyn Tablebase>) {
+        self.tablebase = Arc::from(tablebase);
+    }
 
-            0
-        });
+    /// Set whether or not the searcher should use a transposition table to lookup previous evaluations.
+    pub fn use_table(&mut self, setting: bool) {
+        self.settings.use_table = setting;
     }
-}
 
-#[inline]
-fn evaled_moves(moves: &[Move]) -> Vec<EvaledMove> {
-    moves
-        .iter()
-        .map(|mv| EvaledMove { mv: *mv, eval: 0 })
-        .collect_vec()
-}
+    /// Set whether or not the transposition table is cleared at the start of every search. By
+    /// default it is kept, which makes the searches of consecutive moves in a game faster.
+    #[allow(dead_code)]
+    pub fn clear_between_moves(&mut self, setting: bool) {
+        self.settings.clear_between_moves = setting;
+    }
 
-pub const MVV_LVA: [[isize; 6]; 6] = [
-    [0, 0, 0, 0, 0, 0],       // victim K, attacker K, Q, R, B, N, P, None
-    [50, 51, 52, 53, 54, 55], // victim Q, attacker K, Q, R, B, N, P, None
-    [40, 41, 42, 43, 44, 45], // victim R, attacker K, Q, R, B, N, P, None
-    [30, 31, 32, 33, 34, 35], // victim B, attacker K, Q, R, B, N, P, None
-    [20, 21, 22, 23, 24, 25], // victim N, attacker K, Q, R, B, N, P, None
-    [10, 11, 12, 13, 14, 15], // victim P, attacker K, Q, R, B, N, P, None
-];
+    /// Set whether or not null move pruning should be used.
+    pub fn use_null_move_pruning(&mut self, setting: bool) {
+        self.settings.use_nmp = setting;
+    }
 
-#[cfg(test)]
-mod test {
-    use super::evaled_moves;
-    use crate::chess_move::MoveType;
-    use crate::fen::parse_fen;
-    use crate::search::alpha_beta::AlphaBeta;
-    use crate::search::search::Searcher;
-    use crate::square::SquareIndex::C5;
+    /// Set whether or not late moves should be searched at a reduced depth.
+    pub fn use_late_move_reductions(&mut self, setting: bool) {
+        self.settings.use_lmr = setting;
+    }
 
-    #[test]
-    fn finds_mate_in_one_as_white() {
-        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
-        let mut searcher: AlphaBeta = Searcher::new();
-        let mv = searcher.best_move(&mut pos).mv;
-        assert_eq!(mv.to, 49)
+    /// Set whether or not quiet moves which cannot raise alpha should be pruned near the leaves.
+    pub fn use_futility_pruning(&mut self, setting: bool) {
+        self.settings.use_fp = setting;
+    }
+
+    /// Set the number of moves tried at a node before late move pruning skips its remaining
+    /// quiet moves, where the first count applies one ply from the leaves, the second count two
+    /// plies from the leaves, and so on. Nodes further from the leaves are never pruned, so an
+    /// empty slice turns late move pruning off.
+    #[allow(dead_code)]
+    pub fn late_move_pruning(&mut self, counts: &[usize]) {
+        self.settings.late_move_counts = counts.to_vec();
+    }
+
+    /// Set the margin by which the static evaluation of a frontier node must fall short of alpha
+    /// for its quiet moves to be pruned.
+    #[allow(dead_code)]
+    pub fn futility_margin(&mut self, margin: isize) {
+        self.settings.futility_margin = margin;
+    }
+
+    /// Set the margin per remaining ply by which the static evaluation of a shallow node must
+    /// exceed beta for it to be pruned by reverse futility pruning.
+    #[allow(dead_code)]
+    pub fn reverse_futility_margin(&mut self, margin: isize) {
+        self.settings.reverse_futility_margin = margin;
+    }
+
+    /// Configure null move pruning, setting the depth reduction used for the null move search, the
+    /// minimum remaining depth at which a null move is tried, and the minimum remaining depth at
+    /// which null move cutoffs are verified with a regular reduced depth search, or `None` to
+    /// never verify them.
+    #[allow(dead_code)]
+    pub fn null_move(&mut self, reduction: u8, min_depth: u8, verify_depth: Option<u8>) {
+        self.settings.nmp_reduction = reduction;
+        self.settings.nmp_min_depth = min_depth;
+        self.settings.nmp_verify_depth = verify_depth;
+    }
+
+    /// Set whether or not null moves are skipped when the side to move only has pawns left, where
+    /// zugzwang is common enough that passing would often be wrongly judged safe.
+    #[allow(dead_code)]
+    pub fn null_move_material_guard(&mut self, setting: bool) {
+        self.settings.nmp_material_guard = setting;
+    }
+
+    /// Set whether or not the best root move of each iteration should be searched first in the
+    /// next iteration.
+    pub fn root_move_ordering(&mut self, setting: bool) {
+        self.settings.use_root_ordering = setting;
+    }
+
+    /// Set the number of plies at the start of the quiescence search in which quiet checking
+    /// moves are searched in addition to captures. A value of 0 only searches captures.
+    pub fn quiescence_checks(&mut self, plies: usize) {
+        self.settings.q_check_plies = plies;
+    }
+
+    /// Search each iteration after the first two in a window of `delta` around the previous score,
+    /// widening it on a fail high or fail low as described in `aspiration_search`.
+    #[allow(dead_code)]
+    pub fn aspiration_window(&mut self, delta: isize) {
+        self.settings.aspiration_delta = Some(delta);
+    }
+
+    /// Search every iteration with a full window.
+    pub fn clear_aspiration_window(&mut self) {
+        self.settings.aspiration_delta = None;
+    }
+
+    /// Return the number of times the most recent search had to repeat an iteration because its
+    /// score fell outside of the aspiration window.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn aspiration_researches(&self) -> usize {
+        self.researches
+    }
+
+    /// Remove any time limit previously set with `move_time`, so that searches only end once the
+    /// requested depth is reached or the search is stopped.
+    pub fn clear_move_time(&mut self) {
+        self.settings.move_time = None;
+    }
+
+    /// Limit searches to the given number of nodes, after which the best move found so far is
+    /// returned. The limit is checked on entering every node, so it is overshot by at most the
+    /// moves which complete one after another while the search returns from a line.
+    pub fn node_limit(&mut self, nodes: usize) {
+        self.settings.node_limit = Some(nodes);
+    }
+
+    /// Remove any node limit previously set with `node_limit`.
+    pub fn clear_node_limit(&mut self) {
+        self.settings.node_limit = None;
+    }
+
+    /// Search for a mate in at most the given number of moves, as asked for by UCI `go mate`.
+    /// Iterative deepening then ends as soon as such a mate is found.
+    pub fn mate_search(&mut self, moves: usize) {
+        self.settings.mate_moves = Some(moves);
+    }
+
+    /// Remove any mate search previously set with `mate_search`.
+    pub fn clear_mate_search(&mut self) {
+        self.settings.mate_moves = None;
+    }
+
+    /// Returns whether or not a mate search is running and the given score of an iteration to
+    /// the given depth is a mate for the player to move within the number of moves searched for.
+    fn found_mate(&self, eval: isize, depth: usize) -> bool {
+        match (self.settings.mate_moves, mate_distance(eval, depth)) {
+            (Some(limit), Some(moves)) => {
+                usize::try_from(moves).is_ok_and(|moves| moves > 0 && moves <= limit)
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the number of nodes searched by the most recent search, across all iterations.
+    #[must_use]
+    pub fn nodes_searched(&self) -> usize {
+        self.nodes_searched + self.stats.nodes
+    }
+
+    /// Return a flag which, when set, causes any search in progress to end as if its time had
+    /// expired. The flag is not reset automatically.
+    #[must_use]
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Return the move which the opponent is expected to reply with after the given best move,
+    /// taken from the principal variation of the last search.
+    pub fn ponder_move(&self, pos: &mut BoardState, best_move: Move) -> Option<Move> {
+        let pv = self.table.pv(pos, &self.zobrist);
+        if pv.first().map(|e| e.mv) != Some(best_move) {
+            return None;
+        }
+
+        let ponder_move = pv.get(1)?.mv;
+        let new_pos = pos.clone_with_move(best_move);
+        if self.gen.all_moves(&new_pos).contains(&ponder_move) {
+            Some(ponder_move)
+        } else {
+            None
+        }
+    }
+
+    /// Return the depth of the deepest iteration completed by the most recent search.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn completed_depth(&self) -> usize {
+        self.completed_depth
+    }
+
+    /// Print the UCI `info` lines describing a completed iteration of the search.
+    fn print_info(&self, pos: &mut BoardState, best_move: EvaledMove, depth: usize) {
+        for line in self.info_lines(pos, best_move, depth) {
+            println!("{line}");
+        }
+    }
+
+    /// Print an `info` line with the nodes searched so far in all iterations, unless one was
+    /// printed less than `progress_interval` milliseconds ago. The clock is only read every
+    /// `PROGRESS_CHECK_NODES` nodes.
+    fn report_progress(&mut self) {
+        if !self.settings.progress_info && !self.settings.debug {
+            return;
+        }
+        self.progress_checks += 1;
+        if !self.progress_checks.is_multiple_of(PROGRESS_CHECK_NODES) {
+            return;
+        }
+
+        let elapsed = self.start_time.elapsed().as_millis();
+        if elapsed < self.last_progress + self.settings.progress_interval {
+            return;
+        }
+
+        self.last_progress = elapsed;
+        self.progress_reports += 1;
+        let nodes = self.nodes_searched();
+        println!(
+            "info nodes {} nps {} time {}",
+            nodes,
+            nodes as u128 * 1000 / elapsed.max(1),
+            elapsed
+        );
+    }
+
+    /// Return the number of progress `info` lines printed during the most recent search.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn progress_reports(&self) -> usize {
+        self.progress_reports
+    }
+
+    /// Return the `info` line describing a completed iteration of the search, followed by
+    /// `info string` diagnostics on the aspiration window and time limit when debugging.
+    fn info_lines(&self, pos: &mut BoardState, best_move: EvaledMove, depth: usize) -> Vec<String> {
+        let pv = self
+            .table
+            .pv(pos, &self.zobrist)
+            .iter()
+            .map(|e| e.mv.to_algebraic())
+            .join(" ");
+        let elapsed = self.start_time.elapsed().as_millis();
+        let score = match mate_distance(best_move.eval, depth) {
+            Some(moves) => format!("mate {moves}"),
+            None => format!("cp {}", best_move.eval),
+        };
+        let mut lines = vec![format!(
+            "info depth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+            depth,
+            score,
+            self.stats.nodes,
+            self.stats.nps(),
+            elapsed,
+            self.table.usage_permille(),
+            pv
+        )];
+
+        if self.settings.debug {
+            lines.push(format!(
+                "info string aspiration researches {}",
+                self.researches
+            ));
+            if let Some(move_time) = self.settings.move_time {
+                lines.push(format!("info string time used {elapsed} of {move_time} ms"));
+            }
+        }
+        lines
+    }
+
+    /// Returns whether or not the search should end, either because it was stopped or because
+    /// its time or node limit has been reached.
+    fn time_expired(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        if let Some(limit) = self.settings.node_limit {
+            if self.nodes_searched() >= limit {
+                return true;
+            }
+        }
+
+        if self.settings.move_time.is_none() {
+            return false;
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.start_time).as_millis();
+        self.settings.move_time.unwrap() < elapsed
+    }
+
+    /// Appends the sorted legal moves of the position to the moves seeded from the table, the
+    /// previous iteration, or IID. A seeded move may come from a different position with the same
+    /// hash, so any which are not legal here are dropped rather than searched.
+    fn append_legal_moves(&self, pos: &BoardState, moves: &mut Vec<EvaledMove>) {
+        let legal = self.gen.all_moves(pos);
+        moves.retain(|mv| legal.contains(&mv.mv));
+
+        let mut gen = evaled_moves(&legal);
+        self.sort_moves(&mut gen, pos);
+        moves.append(&mut gen);
+    }
+
+    fn sort_moves(&self, moves: &mut [EvaledMove], pos: &BoardState) {
+        if !self.settings.use_move_ordering {
+            return;
+        }
+
+        // The pieces involved in each capture are looked up once, before sorting, and only for
+        // the moves which actually capture on their destination square.
+        let mut keyed = moves
+            .iter()
+            .map(|mv| {
+                let key = capture_pieces(pos, mv.mv).map_or(0, |(attacker, victim)| {
+                    MVV_LVA[victim.idx()][attacker.idx()] - 100
+                });
+                (key, *mv)
+            })
+            .collect_vec();
+        keyed.sort_by_key(|(key, _)| *key);
+
+        for (mv, (_, sorted)) in moves.iter_mut().zip(keyed) {
+            *mv = sorted;
+        }
+    }
+}
+
+/// Return the moving and the captured piece of a move which captures on its destination square,
+/// or `None` for quiet moves and en passant captures.
+fn capture_pieces(pos: &BoardState, mv: Move) -> Option<(PieceType, PieceType)> {
+    if !mv.is_capture() || mv.is_en_passant_capture() {
+        return None;
+    }
+    Some((pos.type_on(mv.from)?, pos.type_on(mv.to)?))
+}
+
+#[inline]
+fn evaled_moves(moves: &[Move]) -> Vec<EvaledMove> {
+    moves
+        .iter()
+        .map(|mv| EvaledMove { mv: *mv, eval: 0 })
+        .collect_vec()
+}
+
+pub const MVV_LVA: [[isize; 6]; 6] = [
+    [0, 0, 0, 0, 0, 0],       // victim K, attacker K, Q, R, B, N, P, None
+    [50, 51, 52, 53, 54, 55], // victim Q, attacker K, Q, R, B, N, P, None
+    [40, 41, 42, 43, 44, 45], // victim R, attacker K, Q, R, B, N, P, None
+    [30, 31, 32, 33, 34, 35], // victim B, attacker K, Q, R, B, N, P, None
+    [20, 21, 22, 23, 24, 25], // victim N, attacker K, Q, R, B, N, P, None
+    [10, 11, 12, 13, 14, 15], // victim P, attacker K, Q, R, B, N, P, None
+];
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{
+        evaled_moves, mate_distance, LATE_MOVE_COUNTS, MVV_LVA, NMP_VERIFY_DEPTH,
+        PROGRESS_CHECK_NODES, PROGRESS_INTERVAL_MS, Q_SEARCH_DEPTH,
+    };
+    use crate::board::BoardState;
+    use crate::chess_move::{EvaledMove, Move, MoveType};
+    use crate::fen::parse_fen;
+    use crate::search::alpha_beta::AlphaBeta;
+    use crate::search::eval::{eval, Eval, INF, MATE_VALUE, NEG_INF};
+    use crate::search::search::Searcher;
+    use crate::search::tablebase::{Tablebase, Wdl};
+    use crate::square::SquareIndex::{B1, B7, C5};
+    use crate::table::{Bound, TranspositionTable};
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn searcher_can_move_to_another_thread() {
+        assert_send::<AlphaBeta>();
+    }
+
+    #[test]
+    fn ignores_illegal_move_from_table() {
+        // Black is stalemated, but the table suggests a king move into check.
+        let mut pos = parse_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let mut searcher = AlphaBeta::new();
+        let stale = EvaledMove {
+            mv: Move {
+                from: 63,
+                to: 62,
+                kind: MoveType::Quiet,
+            },
+            eval: 0,
+        };
+        searcher.save(&mut pos, stale, Bound::Exact, 0);
+
+        let mv = searcher.best_move_depth(&mut pos, 3);
+        assert_eq!(mv.mv.kind, MoveType::Null);
+        assert_eq!(mv.eval, 0);
+    }
+
+    #[test]
+    fn prefers_mate_to_stalemate() {
+        // Qf7 stalemates, while Qg7 and Qh7 are both mate.
+        let fen = "7k/Q7/6K1/8/8/8/8/8 w - - 0 1";
+        for depth in 1..=4 {
+            let mut pos = parse_fen(fen).unwrap();
+            let mut searcher: AlphaBeta = Searcher::new();
+            let mv = searcher.best_move_depth(&mut pos, depth);
+            assert!(mv.eval >= MATE_VALUE, "depth {}", depth);
+
+            let next = pos.clone_with_move(mv.mv);
+            assert!(searcher.gen.all_moves(&next).is_empty());
+            assert!(searcher.gen.is_in_check(&next), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn finds_mate_in_one_as_white() {
+        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let mv = searcher.best_move(&mut pos).mv;
+        assert_eq!(mv.to, 49)
     }
 
     #[test]
@@ -554,6 +1367,615 @@ mod test {
         assert_ne!(mv.mv.to, 17)
     }
 
+    #[test]
+    fn late_move_reductions_do_not_hide_quiet_mate() {
+        // Kb6 is a quiet king move generated late, which only wins once Rh8 mate is seen two
+        // plies later.
+        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        for use_lmr in [true, false] {
+            let mut searcher: AlphaBeta = Searcher::new();
+            searcher.settings.use_lmr = use_lmr;
+            let mv = searcher.best_move_depth(&mut pos, 4);
+            assert_eq!(mv.mv.to_algebraic(), "c6b6");
+            assert!(mv.eval > MATE_VALUE);
+        }
+    }
+
+    /// Positions which each hold a tactic that a search to depth 4 finds.
+    const TACTICAL_FENS: [&str; 4] = [
+        "k7/8/2K5/8/8/8/8/1Q6 w - - 0 1",
+        "rnbqkbnr/7p/pppPpBp1/8/8/3P4/PPP2PPP/R2QKBNR b - - 0 1",
+        "7k/8/r7/r7/8/8/p1RR3K/8 w - - 0 1",
+        "2Q5/1K6/5k2/8/3bB3/8/8/8 b - - 0 72",
+    ];
+
+    /// Search each tactical position to depth 4 with a searcher configured one way and then the
+    /// other, returning the results of both searches.
+    fn search_tactics(
+        configure: impl Fn(&mut AlphaBeta, bool),
+    ) -> Vec<(&'static str, EvaledMove, EvaledMove)> {
+        let search = |fen: &str, setting: bool| {
+            let mut searcher: AlphaBeta = Searcher::new();
+            configure(&mut searcher, setting);
+            searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 4)
+        };
+        TACTICAL_FENS
+            .iter()
+            .map(|fen| (*fen, search(fen, true), search(fen, false)))
+            .collect()
+    }
+
+    /// Assert that configuring a searcher one way or the other finds the same move in each
+    /// tactical position.
+    fn assert_keeps_tactics(configure: impl Fn(&mut AlphaBeta, bool)) {
+        for (fen, on, off) in search_tactics(configure) {
+            assert_eq!(on.mv, off.mv, "{fen}");
+        }
+    }
+
+    /// Return the number of nodes searched to the given depth with a searcher configured one way
+    /// and then the other.
+    fn nodes_with_setting(
+        fen: &str,
+        depth: usize,
+        configure: impl Fn(&mut AlphaBeta, bool),
+    ) -> (usize, usize) {
+        let search = |setting: bool| {
+            let mut searcher: AlphaBeta = Searcher::new();
+            configure(&mut searcher, setting);
+            searcher.best_move_depth(&mut parse_fen(fen).unwrap(), depth);
+            searcher.nodes_searched()
+        };
+        (search(true), search(false))
+    }
+
+    #[test]
+    fn frontier_futility_pruning_keeps_tactics() {
+        let configure = |searcher: &mut AlphaBeta, on: bool| {
+            searcher.futility_margin(if on { 200 } else { INF });
+        };
+        assert_keeps_tactics(configure);
+
+        // A margin which can never be reached turns frontier pruning off.
+        let quiet = "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4";
+        let (pruned, full) = nodes_with_setting(quiet, 5, configure);
+        assert!(pruned < full);
+    }
+
+    #[test]
+    fn futility_pruning_only_at_frontier_nodes() {
+        let mut pos =
+            parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4")
+                .unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let hash = searcher.zobrist.hash(&mut pos);
+        assert!(searcher.frontier_eval(&pos, hash, 1, true).is_some());
+        assert!(searcher.frontier_eval(&pos, hash, 1, false).is_none());
+        assert!(searcher.frontier_eval(&pos, hash, 2, true).is_none());
+    }
+
+    #[test]
+    fn reverse_futility_pruning_keeps_tactics() {
+        let configure = |searcher: &mut AlphaBeta, on: bool| {
+            searcher.reverse_futility_margin(if on { 150 } else { INF });
+        };
+        assert_keeps_tactics(configure);
+
+        // Below the root many nodes are already far ahead of beta by their static evaluation.
+        let italian = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let (pruned, full) = nodes_with_setting(italian, 5, configure);
+        assert!(pruned < full);
+    }
+
+    #[test]
+    fn late_move_pruning_keeps_tactics() {
+        let configure = |searcher: &mut AlphaBeta, on: bool| {
+            searcher.late_move_pruning(if on { &LATE_MOVE_COUNTS[..] } else { &[] });
+        };
+        assert_keeps_tactics(configure);
+
+        // Quiet positions have plenty of moves which do not improve on the first few.
+        let quiet = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let (pruned, full) = nodes_with_setting(quiet, 5, configure);
+        assert!(pruned < full);
+    }
+
+    #[test]
+    fn null_move_material_guard_handles_pawn_endings() {
+        // The pawn outruns the black king, which only gets in front of it if black may pass. Null
+        // moves are neither verified nor limited by depth, so only the material guard stops them.
+        let fen = "8/8/5p2/7P/8/8/2k5/7K w - - 0 1";
+        let search = |guard: bool| {
+            let mut searcher: AlphaBeta = Searcher::new();
+            searcher.null_move(2, 1, None);
+            searcher.null_move_material_guard(guard);
+            searcher.quiescence_checks(0);
+            searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 6)
+        };
+
+        let guarded = search(true);
+        assert_eq!(guarded.mv.to_algebraic(), "h5h6");
+        assert!(guarded.eval > 500);
+        assert!(search(false).eval < guarded.eval);
+    }
+
+    #[test]
+    fn null_move_verification_handles_zugzwang() {
+        // After Kh6 black is in zugzwang: passing would hold, but every legal move loses material.
+        // An unverified null move search prunes Kh6 and settles for Rxd7 instead.
+        // Quiet checks are left out of quiescence so that only the null move search is exercised.
+        let mut pos = parse_fen("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.null_move(2, 3, Some(3));
+        searcher.quiescence_checks(0);
+        let mv = searcher.best_move_depth(&mut pos, 6);
+        assert_eq!(mv.mv.to_algebraic(), "g5h6");
+    }
+
+    #[test]
+    fn shallow_null_move_cutoffs_are_not_verified() {
+        let configure = |searcher: &mut AlphaBeta, deep_only: bool| {
+            let verify_depth = if deep_only { NMP_VERIFY_DEPTH } else { 1 };
+            searcher.null_move(2, 3, Some(verify_depth));
+        };
+        assert_keeps_tactics(configure);
+
+        let quiet = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let (deep_only, every_depth) = nodes_with_setting(quiet, 6, configure);
+        assert!(deep_only < every_depth);
+    }
+
+    #[test]
+    fn aspiration_window_widens_to_the_full_window_score() {
+        let fen = "r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8";
+
+        let mut full: AlphaBeta = Searcher::new();
+        full.clear_aspiration_window();
+        let expected = full.best_move_depth(&mut parse_fen(fen).unwrap(), 5);
+        assert_eq!(full.aspiration_researches(), 0);
+
+        // A window this narrow fails on almost every iteration, and has to be widened before the
+        // score can be trusted.
+        let mut narrow: AlphaBeta = Searcher::new();
+        narrow.aspiration_window(1);
+        let actual = narrow.best_move_depth(&mut parse_fen(fen).unwrap(), 5);
+        assert!(narrow.aspiration_researches() > 0);
+        assert_eq!(actual.eval, expected.eval);
+        assert_eq!(actual.mv, expected.mv);
+    }
+
+    #[test]
+    fn mate_search_stops_at_first_mate() {
+        let mut pos = parse_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.mate_search(2);
+        let mv = searcher.best_move_depth(&mut pos, 10);
+
+        assert!(["d1d8", "d2d8"].contains(&mv.mv.to_algebraic().as_str()));
+        // The mate is scored by quiescence at depth 3, but only measured by the main search once it
+        // searches the mating move and both replies.
+        assert_eq!(searcher.completed_depth(), 4);
+        assert_eq!(mate_distance(mv.eval, 4), Some(2));
+        let lines = searcher.info_lines(&mut pos, mv, 4);
+        assert!(lines[0].contains("score mate 2 "), "{}", lines[0]);
+    }
+
+    #[test]
+    fn mate_distance_counts_moves() {
+        assert_eq!(mate_distance(100, 4), None);
+        assert_eq!(mate_distance(MATE_VALUE, 1), None);
+        assert_eq!(mate_distance(MATE_VALUE + 1, 2), Some(1));
+        assert_eq!(mate_distance(MATE_VALUE + 2, 5), Some(2));
+        assert_eq!(mate_distance(-MATE_VALUE - 1, 3), Some(-1));
+    }
+
+    #[test]
+    fn debug_adds_info_strings() {
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let is_info_string = |line: &String| line.starts_with("info string");
+
+        let mv = searcher.best_move_depth(&mut pos, 3);
+        let lines = searcher.info_lines(&mut pos, mv, 3);
+        assert_eq!(lines.len(), 1);
+        assert!(!lines.iter().any(is_info_string));
+
+        searcher.debug(true);
+        let mv = searcher.best_move_depth(&mut pos, 3);
+        let lines = searcher.info_lines(&mut pos, mv, 3);
+        assert!(lines[0].starts_with("info depth 3"));
+        assert!(lines[1..].iter().all(is_info_string));
+        assert!(lines.len() > 1);
+    }
+
+    /// Scores positions by material alone, optionally inverted so that losing material is good.
+    struct MaterialEval {
+        sign: isize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Eval for MaterialEval {
+        fn evaluate(&self, pos: &BoardState) -> isize {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.sign * (pos.material(pos.active_player) - pos.material(!pos.active_player))
+        }
+    }
+
+    #[test]
+    fn search_uses_custom_eval() {
+        // The rook can take a free queen.
+        let fen = "3qk3/8/8/8/8/8/8/3RK3 w - - 0 1";
+        let mut best_moves = Vec::new();
+        for sign in [1, -1].iter() {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let mut searcher: AlphaBeta = Searcher::new();
+            searcher.set_eval(Box::new(MaterialEval {
+                sign: *sign,
+                calls: Arc::clone(&calls),
+            }));
+            let mv = searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 2);
+            assert!(calls.load(Ordering::Relaxed) > 0);
+            best_moves.push(mv.mv.to_algebraic());
+        }
+        assert_eq!(best_moves[0], "d1d8");
+        assert_ne!(best_moves[1], "d1d8");
+    }
+
+    #[test]
+    fn eval_cache_is_hit_on_transpositions() {
+        let mut searcher: AlphaBeta = Searcher::new();
+        let mut pos =
+            parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let hash = searcher.zobrist.hash(&mut pos);
+        assert_eq!(searcher.static_eval(&pos, hash), eval(&pos));
+        assert_eq!(searcher.static_eval(&pos, hash), eval(&pos));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        searcher.set_eval(Box::new(MaterialEval {
+            sign: 1,
+            calls: Arc::clone(&calls),
+        }));
+        for line in [["g1f3", "g8f6", "b1c3"], ["b1c3", "g8f6", "g1f3"]].iter() {
+            let mut pos = BoardState::default();
+            for mv in line.iter() {
+                let mv = searcher
+                    .gen
+                    .all_moves(&pos)
+                    .into_iter()
+                    .find(|m| m.to_algebraic() == *mv);
+                pos.make_move(mv.unwrap());
+            }
+            let hash = searcher.zobrist.hash(&mut pos);
+            assert_eq!(searcher.static_eval(&pos, hash), 0);
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn eval_cache_is_hit_by_quiescence() {
+        // Nothing is pruned one ply from the root, so every static evaluation of a one ply search
+        // comes from quiescence search.
+        let mut pos =
+            parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        searcher.set_eval(Box::new(MaterialEval {
+            sign: 1,
+            calls: Arc::clone(&calls),
+        }));
+        searcher.best_move_depth(&mut pos, 1);
+        assert!(searcher.eval_cache.hits() > 0);
+        assert!(calls.load(Ordering::Relaxed) > 0);
+    }
+
+    /// A tablebase holding every position with up to four pieces, each with the same result.
+    struct FixedTablebase {
+        result: Option<Wdl>,
+        probes: Arc<AtomicUsize>,
+    }
+
+    impl Tablebase for FixedTablebase {
+        fn max_pieces(&self) -> u32 {
+            4
+        }
+
+        fn probe_wdl(&self, _pos: &BoardState) -> Option<Wdl> {
+            self.probes.fetch_add(1, Ordering::Relaxed);
+            self.result
+        }
+    }
+
+    #[test]
+    fn tablebase_misses_leave_search_unchanged() {
+        let fen = "3qk3/8/8/8/8/8/8/3RK3 w - - 0 1";
+        let mut results = Vec::new();
+        for result in [None, Some(None)].iter() {
+            let probes = Arc::new(AtomicUsize::new(0));
+            let mut searcher: AlphaBeta = Searcher::new();
+            searcher.use_table(false);
+            if let Some(result) = result {
+                searcher.set_tablebase(Box::new(FixedTablebase {
+                    result: *result,
+                    probes: Arc::clone(&probes),
+                }));
+            }
+            let mv = searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 4);
+            results.push((mv.mv, mv.eval, searcher.nodes_searched()));
+            assert_eq!(probes.load(Ordering::Relaxed) > 0, result.is_some());
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn tablebase_hits_replace_search() {
+        // Taking the queen would win, but the tablebase says every position is drawn.
+        let fen = "3qk3/8/8/8/8/8/8/3RK3 w - - 0 1";
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.set_tablebase(Box::new(FixedTablebase {
+            result: Some(Wdl::Draw),
+            probes: Arc::new(AtomicUsize::new(0)),
+        }));
+        let mv = searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 4);
+        assert_eq!(mv.eval, 0);
+
+        assert!(Wdl::Win.score(1) > Wdl::Win.score(3));
+        assert!(Wdl::Win.score(1) < MATE_VALUE);
+        assert_eq!(Wdl::Loss.score(2), -Wdl::Win.score(2));
+    }
+
+    #[test]
+    fn search_result_is_consistent() {
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let result = searcher.search(&mut pos, 4);
+
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.pv[0], result.best_move);
+        assert_eq!(result.nodes, searcher.nodes_searched() as u64);
+        assert!(result.nodes > 0);
+
+        // Every move of the principal variation must be legal in turn.
+        let mut line = pos;
+        for mv in &result.pv {
+            assert!(searcher.gen.all_moves(&line).contains(mv));
+            line = line.clone_with_move(*mv);
+        }
+
+        let mut stalemate = parse_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = searcher.search(&mut stalemate, 3);
+        assert_eq!(result.best_move.kind, MoveType::Null);
+        assert!(result.pv.is_empty());
+    }
+
+    #[test]
+    fn long_search_reports_progress() {
+        // Without an interval, every check of the clock reports, so the number of reports only
+        // depends on the number of nodes searched.
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.progress_interval(0);
+        searcher.node_limit(PROGRESS_CHECK_NODES * 8);
+        searcher.best_move_depth(&mut pos, 64);
+        assert_eq!(searcher.progress_reports(), 0);
+
+        searcher.progress_info(true);
+        searcher.best_move_depth(&mut pos, 64);
+        assert!(searcher.progress_reports() > 1);
+
+        searcher.progress_interval(PROGRESS_INTERVAL_MS);
+        searcher.best_move_depth(&mut pos, 1);
+        assert_eq!(searcher.progress_reports(), 0);
+    }
+
+    #[test]
+    fn aborted_iteration_is_discarded() {
+        // Without the table the search is deterministic, so a node limit which falls halfway
+        // through the fifth iteration stops the search at the same point every time.
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.use_table(false);
+        let completed = searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 4);
+        let completed_nodes = searcher.nodes_searched();
+        searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 5);
+        let full_nodes = searcher.nodes_searched();
+
+        searcher.node_limit((completed_nodes + full_nodes) / 2);
+        let aborted = searcher.best_move_depth(&mut parse_fen(fen).unwrap(), 5);
+        assert_eq!(searcher.completed_depth(), 4);
+        assert_eq!(aborted.mv, completed.mv);
+        assert_eq!(aborted.eval, completed.eval);
+    }
+
+    #[test]
+    fn table_is_reused_between_moves() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let mut nodes = Vec::new();
+        for clear in [false, true].iter() {
+            let mut pos = parse_fen(fen).unwrap();
+            let mut searcher: AlphaBeta = Searcher::new();
+            searcher.clear_between_moves(*clear);
+            let mv = searcher.best_move_depth(&mut pos, 5);
+            pos.make_move(mv.mv);
+            searcher.best_move_depth(&mut pos, 5);
+            nodes.push(searcher.nodes_searched());
+        }
+        assert!(nodes[0] < nodes[1], "{:?}", nodes);
+    }
+
+    #[test]
+    fn root_move_ordering_survives_clobbered_table() {
+        // With the table disabled no entry survives between iterations, which is the worst case
+        // of the table being overwritten.
+        let mut pos =
+            parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.use_table(false);
+        let best = searcher.best_move_depth(&mut pos, 4).mv;
+        let hash = searcher.zobrist.hash(&mut pos);
+        let (_, hint) = searcher.probe(hash, NEG_INF, INF, 0);
+        assert_eq!(hint, None);
+
+        let first = |searcher: &AlphaBeta, hint, ply| {
+            searcher
+                .first_moves(hint, ply)
+                .iter()
+                .map(|e| e.mv)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(first(&searcher, hint, 0), vec![best]);
+        assert_eq!(first(&searcher, hint, 1), vec![]);
+
+        // The table's move comes second, unless it is the previous best move.
+        let other = searcher
+            .gen
+            .all_moves(&pos)
+            .into_iter()
+            .find(|mv| *mv != best)
+            .unwrap();
+        assert_eq!(first(&searcher, Some(other), 0), vec![best, other]);
+        assert_eq!(first(&searcher, Some(best), 0), vec![best]);
+
+        searcher.root_move_ordering(false);
+        assert_eq!(first(&searcher, hint, 0), vec![]);
+    }
+
+    #[test]
+    fn perpetual_check_is_a_draw() {
+        // White is two queens down, but Qh5+ Kg8 Qe8+ Kh7 repeats forever.
+        let mut pos = parse_fen("8/6pk/8/8/8/4K3/q7/q2Q4 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let mv = searcher.best_move_depth(&mut pos, 6);
+        assert_eq!(mv.mv.to_algebraic(), "d1h5");
+        assert_eq!(mv.eval, 0);
+
+        // The draw only holds on this line, so it is not saved in the table.
+        let hash = searcher.zobrist.hash(&mut pos);
+        assert!(searcher
+            .table
+            .get(hash)
+            .is_some_and(|e| e.best_move.eval != 0));
+    }
+
+    #[test]
+    fn quiescence_does_not_stand_pat_in_check() {
+        // Black is checkmated despite being far ahead in material, so standing pat on the static
+        // evaluation would wrongly fail high.
+        let mut pos = parse_fen("k7/1Q6/1K6/8/8/8/8/qqqq4 b - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        assert!(eval(&pos) > 100);
+        let hash = searcher.zobrist.hash(&mut pos);
+        let score = searcher
+            .q_search(&mut pos, hash, -100, 100, Q_SEARCH_DEPTH)
+            .unwrap();
+        assert_eq!(score, -MATE_VALUE);
+    }
+
+    #[test]
+    fn quiescence_finds_quiet_check_fork() {
+        // Qxd4 wins a pawn, but runs into the quiet fork Nc2+ which only shows up if quiet checks
+        // are searched in quiescence.
+        let mut pos = parse_fen("5k2/8/8/8/1n1p4/8/8/3QK3 w - - 0 1").unwrap();
+
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.quiescence_checks(0);
+        let mv = searcher.best_move_depth(&mut pos, 1);
+        assert_eq!(mv.mv.to_algebraic(), "d1d4");
+
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.quiescence_checks(1);
+        let mv = searcher.best_move_depth(&mut pos, 1);
+        assert_ne!(mv.mv.to_algebraic(), "d1d4");
+    }
+
+    #[test]
+    fn table_does_not_change_best_move() {
+        for (fen, with, without) in search_tactics(|searcher, on| searcher.use_table(on)) {
+            assert_eq!(with.eval, without.eval, "{fen}");
+            // The hint from the table is searched first, so of several moves with the same score
+            // either may be chosen; the other one must then score the same when searched itself.
+            if with.mv != without.mv {
+                for best in [with, without].iter() {
+                    let mut pos = parse_fen(fen).unwrap().clone_with_move(best.mv);
+                    let mut searcher: AlphaBeta = Searcher::new();
+                    searcher.use_table(false);
+                    let reply = searcher.best_move_depth(&mut pos, 3);
+                    assert_eq!(-reply.eval, best.eval, "{fen}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn probe_matches_bounds_and_depth() {
+        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let hash = searcher.zobrist.hash(&mut pos);
+        let mv = Move {
+            from: B1 as u8,
+            to: B7 as u8,
+            kind: MoveType::Quiet,
+        };
+        let best_move = EvaledMove { mv, eval: 50 };
+
+        let cases = [
+            (Bound::Exact, 3, -100, 100, Some(best_move)),
+            (Bound::Exact, 3, 100, 200, Some(best_move)),
+            (Bound::Lower, 3, -100, 40, Some(best_move)),
+            (Bound::Lower, 3, -100, 60, None),
+            (Bound::Upper, 3, 60, 100, Some(best_move)),
+            (Bound::Upper, 3, 40, 100, None),
+            (Bound::Exact, 1, -100, 100, None),
+        ];
+
+        for (bound, depth, alpha, beta, expected) in cases {
+            searcher.table = TranspositionTable::new(10);
+            searcher.save(&mut pos, best_move, bound, depth);
+
+            let (cutoff, hint) = searcher.probe(hash, alpha, beta, 2);
+            assert_eq!(cutoff, expected);
+            assert_eq!(cutoff.map(|e| e.mv), expected.map(|e| e.mv));
+            assert_eq!(hint, Some(mv));
+        }
+    }
+
+    #[test]
+    fn probe_ignores_other_positions() {
+        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.save(&mut pos, EvaledMove::null(0), Bound::Exact, 5);
+
+        let mut other = parse_fen("k7/8/2K5/8/8/8/8/1Q6 b - - 0 1").unwrap();
+        let hash = searcher.zobrist.hash(&mut other);
+        assert_eq!(searcher.probe(hash, NEG_INF, INF, 0), (None, None));
+    }
+
+    #[test]
+    fn stop_flag_ends_search() {
+        let mut pos = parse_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let mv = searcher.best_move_depth(&mut pos, 5);
+        assert_eq!(mv.mv.kind, MoveType::Null);
+    }
+
+    #[test]
+    fn ponder_move_is_legal_reply() {
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut searcher: AlphaBeta = Searcher::new();
+        let best_move = searcher.best_move_depth(&mut pos, 4).mv;
+        let ponder_move = searcher.ponder_move(&mut pos, best_move).unwrap();
+
+        let new_pos = pos.clone_with_move(best_move);
+        assert!(searcher.gen.all_moves(&new_pos).contains(&ponder_move));
+    }
+
     #[test]
     fn sorts_captures_over_non_captures() {
         // Any piece can capture the opposing queen
@@ -570,6 +1992,35 @@ mod test {
         assert_eq!(top_move.mv.kind, MoveType::Capture);
     }
 
+    #[test]
+    fn sort_order_matches_per_move_lookup() {
+        let fens = [
+            "7k/8/8/2q2Q2/1P6/3N4/5B2/K1R5 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        let searcher: AlphaBeta = Searcher::new();
+        for fen in fens.iter() {
+            let pos = parse_fen(fen).unwrap();
+            let mut moves = evaled_moves(&searcher.gen.all_moves(&pos));
+            let mut expected = moves.clone();
+            expected.sort_by_cached_key(|mv| {
+                if mv.mv.is_capture() && !mv.mv.is_en_passant_capture() {
+                    let attacker = pos.type_on(mv.mv.from).unwrap();
+                    let victim = pos.type_on(mv.mv.to).unwrap();
+                    MVV_LVA[victim.idx()][attacker.idx()] - 100
+                } else {
+                    0
+                }
+            });
+
+            searcher.sort_moves(&mut moves, &pos);
+            let moves = moves.iter().map(|mv| mv.mv).collect::<Vec<Move>>();
+            let expected = expected.iter().map(|mv| mv.mv).collect::<Vec<Move>>();
+            assert_eq!(moves, expected);
+        }
+    }
+
     #[test]
     fn sorts_better_captures_over_other_captures() {
         // Rook can take either pawn or queen
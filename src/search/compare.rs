@@ -0,0 +1,113 @@
+use crate::chess_move::EvaledMove;
+use crate::fen::parse_fen;
+use crate::search::search::Searcher;
+
+/// A position in which two searchers chose different best moves.
+#[derive(Debug)]
+pub struct Disagreement {
+    pub fen: String,
+    pub first: EvaledMove,
+    pub second: EvaledMove,
+}
+
+/// Search every position with both searchers to the given depth, returning the positions in
+/// which they chose a different best move. This is useful for checking that a change to the
+/// search, such as a new pruning technique, does not change its result.
+///
+/// # Errors
+///
+/// Returns an error if any of the FEN strings cannot be parsed.
+pub fn compare_searchers<A: Searcher, B: Searcher>(
+    first: &mut A,
+    second: &mut B,
+    fens: &[&str],
+    depth: usize,
+) -> Result<Vec<Disagreement>, String> {
+    let mut disagreements = Vec::new();
+    for fen in fens {
+        let pos = parse_fen(fen)?;
+        let first_move = first.best_move_depth(&mut pos.clone(), depth);
+        let second_move = second.best_move_depth(&mut pos.clone(), depth);
+        if first_move.mv != second_move.mv {
+            disagreements.push(Disagreement {
+                fen: (*fen).to_string(),
+                first: first_move,
+                second: second_move,
+            });
+        }
+    }
+    Ok(disagreements)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::BoardState;
+    use crate::move_gen::MoveGenerator;
+    use crate::search::alpha_beta::AlphaBeta;
+    use crate::search::stats::Stats;
+
+    /// Plays the last legal move, which only agrees with a real search by chance.
+    struct LastMoveSearcher {
+        gen: MoveGenerator,
+        stats: Stats,
+    }
+
+    impl Searcher for LastMoveSearcher {
+        fn new() -> Self {
+            LastMoveSearcher {
+                gen: MoveGenerator::new(),
+                stats: Stats::new(),
+            }
+        }
+
+        fn stats(&self) -> &Stats {
+            &self.stats
+        }
+
+        fn best_move(&mut self, pos: &mut BoardState) -> EvaledMove {
+            self.best_move_depth(pos, 1)
+        }
+
+        fn best_move_depth(&mut self, pos: &mut BoardState, _depth: usize) -> EvaledMove {
+            EvaledMove {
+                mv: *self.gen.all_moves(pos).last().unwrap(),
+                eval: 0,
+            }
+        }
+
+        fn move_time(&mut self, _seconds: u128) {}
+    }
+
+    const FENS: [&str; 2] = [
+        "k7/8/2K5/8/8/8/8/1Q6 w - - 0 1",
+        "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1",
+    ];
+
+    #[test]
+    fn identical_searchers_agree() {
+        let mut first = AlphaBeta::new();
+        let mut second = AlphaBeta::new();
+        second.use_table(false);
+        let disagreements = compare_searchers(&mut first, &mut second, &FENS, 3).unwrap();
+        assert!(disagreements.is_empty());
+    }
+
+    #[test]
+    fn reports_different_best_moves() {
+        let mut first = AlphaBeta::new();
+        let mut second = LastMoveSearcher::new();
+        let disagreements = compare_searchers(&mut first, &mut second, &FENS[1..], 3).unwrap();
+        assert_eq!(disagreements.len(), 1);
+        assert_eq!(disagreements[0].fen, FENS[1]);
+        assert_eq!(disagreements[0].first.mv.to_algebraic(), "d2d5");
+        assert_ne!(disagreements[0].second.mv.to_algebraic(), "d2d5");
+    }
+
+    #[test]
+    fn rejects_invalid_fen() {
+        let mut first = AlphaBeta::new();
+        let mut second = AlphaBeta::new();
+        assert!(compare_searchers(&mut first, &mut second, &["8/8/8 w - - 0 1"], 1).is_err());
+    }
+}
@@ -1,13 +1,12 @@
-use crate::bitboard::PieceItr;
+use crate::bitboard::{
+    file_mask, front_span, rank_mask, Bitboard, New, PieceItr, PopCount, Shift, DARK_SQUARES,
+    FILEA, FILEC, FILED, FILEE, FILEF, FILEH, INIT_W_BISHOPS, INIT_W_KNIGHTS, PASSED_PAWN_MASKS,
+    RANK3, RANK4, RANK5, RANK6,
+};
 use crate::board::BoardState;
+use crate::move_gen::{knight_destinations, pawn_attacks};
 use crate::piece::{Color, PieceType};
-
-const PAWN_VALUE: isize = 100;
-const ROOK_VALUE: isize = 500;
-const KNIGHT_VALUE: isize = 300;
-const BISHOP_VALUE: isize = 300;
-const KING_VALUE: isize = 350;
-const QUEEN_VALUE: isize = 800;
+use crate::square::{square_to_file, square_to_rank, Square};
 
 pub const MATE_VALUE: isize = 31_000;
 pub const INF: isize = 32_001;
@@ -15,6 +14,37 @@ pub const NEG_INF: isize = -32_001;
 
 const MOBILITY_VALUE: isize = 10;
 
+const TEMPO_VALUE: isize = 10;
+
+const MAX_PHASE: isize = 24;
+
+const SPACE_VALUE: isize = 2;
+const CENTER: Bitboard = (FILED | FILEE) & (RANK4 | RANK5);
+const EXTENDED_CENTER: Bitboard = (FILEC | FILED | FILEE | FILEF) & (RANK3 | RANK4 | RANK5 | RANK6);
+
+pub const SCALE_NORMAL: isize = 16;
+const SCALE_ROOK_DRAW: isize = 2;
+const SCALE_DRAW: isize = 0;
+
+const QUEEN_OPEN_FILE_VALUE: isize = 10;
+const QUEEN_SEMI_OPEN_FILE_VALUE: isize = 5;
+
+const DOUBLED_ROOKS_VALUE: isize = 20;
+const CONNECTED_ROOKS_VALUE: isize = 10;
+
+const KING_OPEN_FILE_PENALTY: isize = 25;
+const KING_SEMI_OPEN_FILE_PENALTY: isize = 15;
+
+/// The bonus for a passed pawn on each rank, counted from the pawn's own side of the board.
+const PASSED_PAWN_VALUES: [isize; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+
+const BAD_BISHOP_PAWN_PENALTY: isize = 4;
+const GOOD_BISHOP_VALUE: isize = 10;
+
+const UNDEVELOPED_MINOR_PENALTY: isize = 10;
+const BLOCKED_CENTER_PAWN_PENALTY: isize = 20;
+const MINOR_HOME_SQUARES: Bitboard = INIT_W_KNIGHTS | INIT_W_BISHOPS;
+
 const PAWN_ARRAY_WHITE: [isize; 64] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 2, 3, 4, 4, 3, 2, 0, 0, 4, 6, 10, 10, 6,
     4, 0, 0, 6, 9, 10, 10, 9, 6, 0, 4, 8, 12, 16, 16, 12, 8, 4, 5, 10, 15, 20, 20, 15, 10, 5, 0, 0,
@@ -46,17 +76,45 @@ const WHITE_QUEEN_OPENING: [isize; 64] = [
 ];
 
 const WHITE_KING_OPENING: [isize; 64] = [
-    -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40,
-    -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40, -40, -30,
-    -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20, 30, 10, 0, 0,
-    10, 30, 20,
+    20, 30, 10, 0, 0, 10, 30, 20, 20, 20, 0, 0, 0, 0, 20, 20, -10, -20, -20, -20, -20, -20, -20,
+    -10, -20, -30, -30, -40, -40, -30, -30, -20, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
+    -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50,
+    -40, -40, -30,
+];
+
+const WHITE_KING_ENDGAME: [isize; 64] = [
+    -50, -30, -30, -30, -30, -30, -30, -50, -30, -30, 0, 0, 0, 0, -30, -30, -30, -10, 20, 30, 30,
+    20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10,
+    20, 30, 30, 20, -10, -30, -30, -20, -10, 0, 0, -10, -20, -30, -50, -40, -30, -20, -20, -30,
+    -40, -50,
 ];
 
+/// A static evaluation used by the search, which can be swapped out to experiment with other
+/// evaluation functions. Scores must be relative to the active player, as with `eval`. An
+/// evaluation may be shared between searchers, such as a game's own searcher and its hints.
+pub trait Eval: Send + Sync {
+    fn evaluate(&self, pos: &BoardState) -> isize;
+}
+
+/// The built-in hand-crafted evaluation, see `eval`.
+pub struct DefaultEval;
+
+impl Eval for DefaultEval {
+    fn evaluate(&self, pos: &BoardState) -> isize {
+        eval(pos)
+    }
+}
+
 /// Given a given position, returns an estimated evaluation of the position based on a number of
 /// hand-picked factors such as material difference, center control, tempo, pawn structure, etc.
-/// Evaluations are determined to be relative to the active player.
+/// Evaluations are determined to be relative to the active player: positive scores are good for
+/// the player to move, as required by negamax search. Negate the score when black is to move to
+/// get a white-relative score, as `Game::evaluation_white` does.
+///
+/// Every term other than the tempo bonus is scored from white's point of view, so that the sum
+/// only has to be flipped to the side to move once.
 pub fn eval(pos: &BoardState) -> isize {
-    material_eval(pos)
+    let white_score = material_eval(pos)
         + mobility_eval(pos)
         + pawn_eval(pos)
         + rook_eval(pos)
@@ -64,28 +122,187 @@ pub fn eval(pos: &BoardState) -> isize {
         + bishop_eval(pos)
         + queen_eval(pos)
         + king_eval(pos)
+        + king_file_eval(pos)
+        + development_eval(pos)
+        + space_eval(pos);
+
+    let score = relative(pos, white_score) + tempo_eval(pos);
+    score * scale_factor(pos) / SCALE_NORMAL
 }
 
+/// Returns a score from white's point of view relative to the active player instead.
 #[inline]
-fn material_eval(pos: &BoardState) -> isize {
-    let pawn_eval = piece_difference(pos, PieceType::Pawn) * PAWN_VALUE;
-    let rook_eval = piece_difference(pos, PieceType::Rook) * ROOK_VALUE;
-    let knight_eval = piece_difference(pos, PieceType::Knight) * KNIGHT_VALUE;
-    let bishop_eval = piece_difference(pos, PieceType::Bishop) * BISHOP_VALUE;
-    let queen_eval = piece_difference(pos, PieceType::Queen) * QUEEN_VALUE;
-    let king_eval = piece_difference(pos, PieceType::King) * KING_VALUE;
+fn relative(pos: &BoardState, white_score: isize) -> isize {
+    match pos.active_player {
+        Color::White => white_score,
+        Color::Black => -white_score,
+    }
+}
 
-    pawn_eval + rook_eval + knight_eval + bishop_eval + queen_eval + king_eval
+/// Returns each term of the evaluation by name, relative to the active player and before the
+/// endgame scale factor is applied, followed by the scale factor itself out of `SCALE_NORMAL`.
+/// This is for inspecting the evaluation, so it is kept separate from the faster `eval`.
+#[allow(dead_code)]
+pub fn eval_breakdown(pos: &BoardState) -> Vec<(&'static str, isize)> {
+    vec![
+        ("material", relative(pos, material_eval(pos))),
+        ("mobility", relative(pos, mobility_eval(pos))),
+        ("pawns", relative(pos, pawn_eval(pos))),
+        ("rooks", relative(pos, rook_eval(pos))),
+        ("knights", relative(pos, knight_eval(pos))),
+        ("bishops", relative(pos, bishop_eval(pos))),
+        ("queens", relative(pos, queen_eval(pos))),
+        ("king", relative(pos, king_eval(pos))),
+        ("king files", relative(pos, king_file_eval(pos))),
+        ("development", relative(pos, development_eval(pos))),
+        ("tempo", tempo_eval(pos)),
+        ("space", relative(pos, space_eval(pos))),
+        ("scale", scale_factor(pos)),
+    ]
 }
 
+/// Returns a factor out of `SCALE_NORMAL` by which the evaluation should be scaled, pulling the
+/// score towards a draw in endgames which are known to be drawish despite a material imbalance.
 #[inline]
-fn piece_difference(pos: &BoardState, piece: PieceType) -> isize {
-    num_pieces(pos, pos.active_player, piece) - num_pieces(pos, !pos.active_player, piece)
+fn scale_factor(pos: &BoardState) -> isize {
+    if is_wrong_rook_pawn(pos) {
+        SCALE_DRAW
+    } else if is_drawn_rook_pawn_endgame(pos) {
+        SCALE_ROOK_DRAW
+    } else {
+        SCALE_NORMAL
+    }
 }
 
+/// Recognizes rook and pawn versus rook endgames in which the defending king stands in front of
+/// the pawn, as in the Philidor position. These are drawn with correct defence, so the extra pawn
+/// should not be evaluated as a winning material advantage.
+fn is_drawn_rook_pawn_endgame(pos: &BoardState) -> bool {
+    let minors_or_queens = pos.bb_pieces(PieceType::Knight)
+        | pos.bb_pieces(PieceType::Bishop)
+        | pos.bb_pieces(PieceType::Queen);
+    if !minors_or_queens.is_empty() {
+        return false;
+    }
+
+    let (strong, weak) = match (
+        pos.bb(Color::White, PieceType::Pawn).popcount(),
+        pos.bb(Color::Black, PieceType::Pawn).popcount(),
+    ) {
+        (1, 0) => (Color::White, Color::Black),
+        (0, 1) => (Color::Black, Color::White),
+        _ => return false,
+    };
+
+    if pos.bb(strong, PieceType::Rook).popcount() != 1
+        || pos.bb(weak, PieceType::Rook).popcount() != 1
+    {
+        return false;
+    }
+
+    let pawn = pos.bb(strong, PieceType::Pawn).trailing_zeros() as u8;
+    pos.king_square(weak).is_some_and(|king| {
+        let file_distance =
+            (i16::from(square_to_file(pawn)) - i16::from(square_to_file(king))).abs();
+        let ranks_ahead = i16::from(square_to_rank(king)) - i16::from(square_to_rank(pawn));
+        let in_front = ranks_ahead * i16::from(strong.pawn_push_offset()) > 0;
+
+        file_distance <= 1 && in_front
+    })
+}
+
+/// Recognizes the wrong rook pawn: a king, bishop, and pawns on a single rook file against a
+/// bare king, where the bishop cannot control the promotion square. Once the defending king
+/// reaches the corner in front of the pawns it can never be driven out, so the position is a dead
+/// draw however much material the stronger side has.
+fn is_wrong_rook_pawn(pos: &BoardState) -> bool {
+    let strong = if pos.bb_for_color(Color::Black) == pos.bb(Color::Black, PieceType::King) {
+        Color::White
+    } else if pos.bb_for_color(Color::White) == pos.bb(Color::White, PieceType::King) {
+        Color::Black
+    } else {
+        return false;
+    };
+
+    let pawns = pos.bb(strong, PieceType::Pawn);
+    let bishops = pos.bb(strong, PieceType::Bishop);
+    let king_bishop_and_pawns = pos.bb(strong, PieceType::King) | bishops | pawns;
+    if pawns.is_empty() || bishops.is_empty() || pos.bb_for_color(strong) != king_bishop_and_pawns {
+        return false;
+    }
+
+    let (file, file_bb) = if pawns & !FILEA == 0 {
+        (0, FILEA)
+    } else if pawns & !FILEH == 0 {
+        (7, FILEH)
+    } else {
+        return false;
+    };
+    let promotion_bb = strong.promotion_rank() & file_bb;
+    let promotion = promotion_bb.trailing_zeros();
+
+    // Every bishop must stand on the opposite color to the promotion square.
+    let promotion_is_dark = DARK_SQUARES & promotion_bb != 0;
+    let promotion_color = if promotion_is_dark {
+        DARK_SQUARES
+    } else {
+        !DARK_SQUARES
+    };
+    if bishops & promotion_color != 0 {
+        return false;
+    }
+
+    pos.king_square(!strong).is_some_and(|king| {
+        let file_distance = square_to_file(king).abs_diff(file);
+        let rank_distance = u32::from(square_to_rank(king)).abs_diff(promotion / 8);
+        file_distance <= 1 && rank_distance <= 1
+    })
+}
+
+/// Returns the phase of the game based on the non-pawn material left on the board, ranging from
+/// `MAX_PHASE` in the opening down to 0 once only kings and pawns remain.
+#[inline]
+fn phase(pos: &BoardState) -> isize {
+    pos.phase().min(MAX_PHASE)
+}
+
+/// A small bonus for the active player for having the move, which matters less as the game
+/// approaches the endgame.
 #[inline]
-fn num_pieces(pos: &BoardState, color: Color, piece: PieceType) -> isize {
-    pos.bb(color, piece).iter().count() as isize
+fn tempo_eval(pos: &BoardState) -> isize {
+    TEMPO_VALUE * phase(pos) / MAX_PHASE
+}
+
+/// Rewards occupying and controlling the center with pawns and knights. The four central squares
+/// are counted on top of the wider center, so they are worth more. Space matters most in the
+/// opening and middlegame, so the bonus shrinks with the phase of the game.
+#[inline]
+fn space_eval(pos: &BoardState) -> isize {
+    let space = |color: Color| -> isize {
+        let mut controlled = pos.bb_for_color(color);
+        for (square, _) in pos.bb(color, PieceType::Pawn).iter() {
+            controlled |= pawn_attacks(square, color);
+        }
+        for (square, _) in pos.bb(color, PieceType::Knight).iter() {
+            controlled |= knight_destinations(square);
+        }
+        (controlled & CENTER).popcount() as isize
+            + (controlled & EXTENDED_CENTER).popcount() as isize
+    };
+
+    let score = (space(Color::White) - space(Color::Black)) * SPACE_VALUE;
+    score * phase(pos) / MAX_PHASE
+}
+
+/// Blends the opening and endgame values of the material on the board by the phase of the game,
+/// in the same way as the tapered piece-square tables. Both values are tracked incrementally by
+/// the position.
+#[inline]
+fn material_eval(pos: &BoardState) -> isize {
+    let opening = pos.material(Color::White) - pos.material(Color::Black);
+    let endgame = pos.endgame_material(Color::White) - pos.endgame_material(Color::Black);
+    let phase = phase(pos);
+    (opening * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE
 }
 
 #[inline]
@@ -107,10 +324,31 @@ fn pawn_eval(pos: &BoardState) -> isize {
         black_score += PAWN_ARRAY_WHITE[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
+    white_score += passed_pawns_eval(pos, Color::White);
+    black_score += passed_pawns_eval(pos, Color::Black);
+
+    white_score - black_score
+}
+
+/// Rewards the passed pawns of the given color, which no opposing pawn can stop on their way to
+/// promotion, by how far they have advanced. Only the front pawn of a doubled pair is counted,
+/// and pawns with the opposing king standing on their path are not counted at all, since the
+/// king blockades them as in the Philidor defence.
+fn passed_pawns_eval(pos: &BoardState, color: Color) -> isize {
+    let pawns = pos.bb(color, PieceType::Pawn);
+    let their_pawns = pos.bb(!color, PieceType::Pawn);
+    let their_king = pos.bb(!color, PieceType::King);
+    let back_rank = color.back_rank().trailing_zeros() / 8;
+    let mut score = 0;
+    for (square, _) in pawns.iter() {
+        if PASSED_PAWN_MASKS[color][square as usize] & their_pawns == 0
+            && front_span(square, color) & (pawns | their_king) == 0
+        {
+            let rank = u32::from(square_to_rank(square)).abs_diff(back_rank);
+            score += PASSED_PAWN_VALUES[rank as usize];
+        }
     }
+    score
 }
 
 #[inline]
@@ -127,10 +365,36 @@ fn rook_eval(pos: &BoardState) -> isize {
         black_score += WHITE_ROOK_OPENING[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
+    let coordination = rook_coordination(pos, Color::White) - rook_coordination(pos, Color::Black);
+
+    coordination + white_score - black_score
+}
+
+/// Rewards two rooks of the given color which defend each other, either doubled on a file or
+/// connected along a rank, with no pieces standing between them.
+#[inline]
+fn rook_coordination(pos: &BoardState, color: Color) -> isize {
+    let rooks: Vec<Square> = pos
+        .bb(color, PieceType::Rook)
+        .iter()
+        .map(|(square, _)| square)
+        .collect();
+
+    let mut score = 0;
+    for (i, &a) in rooks.iter().enumerate() {
+        for &b in &rooks[i + 1..] {
+            let (low, high) = (a.min(b), a.max(b));
+            let between = (1 << high) - (1 << (low + 1));
+            if file_mask(a) & file_mask(b) != 0 {
+                if between & file_mask(a) & pos.bb_all() == 0 {
+                    score += DOUBLED_ROOKS_VALUE;
+                }
+            } else if rank_mask(a) & rank_mask(b) != 0 && between & pos.bb_all() == 0 {
+                score += CONNECTED_ROOKS_VALUE;
+            }
+        }
     }
+    score
 }
 
 #[inline]
@@ -147,10 +411,7 @@ fn knight_eval(pos: &BoardState) -> isize {
         black_score += WHITE_KNIGHT_OPENING[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
-    }
+    white_score - black_score
 }
 
 #[inline]
@@ -167,10 +428,32 @@ fn bishop_eval(pos: &BoardState) -> isize {
         black_score += WHITE_BISHOP_OPENING[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
+    white_score += bishop_pawns_eval(pos, Color::White);
+    black_score += bishop_pawns_eval(pos, Color::Black);
+
+    white_score - black_score
+}
+
+/// Scores the bishops of the given color by the pawns of their own color standing on the same
+/// color of square. Each such pawn blocks the bishop's diagonals and makes it a bad bishop, while
+/// a bishop with none of them has its diagonals open and gets a bonus.
+fn bishop_pawns_eval(pos: &BoardState, color: Color) -> isize {
+    let pawns = pos.bb(color, PieceType::Pawn);
+    let mut score = 0;
+    for (square, _) in pos.bb(color, PieceType::Bishop).iter() {
+        let square_color = if DARK_SQUARES & Bitboard::for_square(square) == 0 {
+            !DARK_SQUARES
+        } else {
+            DARK_SQUARES
+        };
+        let blocking = (pawns & square_color).popcount() as isize;
+        score += if blocking == 0 {
+            GOOD_BISHOP_VALUE
+        } else {
+            -blocking * BAD_BISHOP_PAWN_PENALTY
+        };
     }
+    score
 }
 
 #[inline]
@@ -187,30 +470,125 @@ fn queen_eval(pos: &BoardState) -> isize {
         black_score += WHITE_QUEEN_OPENING[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
-    }
+    let file_score = open_file_eval(
+        pos,
+        PieceType::Queen,
+        QUEEN_OPEN_FILE_VALUE,
+        QUEEN_SEMI_OPEN_FILE_VALUE,
+    );
+
+    file_score + white_score - black_score
+}
+
+/// Rewards pieces of the given type which stand on open files (those without any pawns) and
+/// semi-open files (those without any friendly pawns).
+#[inline]
+fn open_file_eval(pos: &BoardState, piece: PieceType, open: isize, semi_open: isize) -> isize {
+    let file_score = |color: Color| -> isize {
+        let friendly_pawns = pos.bb(color, PieceType::Pawn);
+        let enemy_pawns = pos.bb(!color, PieceType::Pawn);
+        let mut score = 0;
+        for (square, _) in pos.bb(color, piece).iter() {
+            let file = file_mask(square);
+            if !(file & friendly_pawns).is_empty() {
+                continue;
+            }
+            score += if (file & enemy_pawns).is_empty() {
+                open
+            } else {
+                semi_open
+            };
+        }
+        score
+    };
+
+    file_score(Color::White) - file_score(Color::Black)
 }
 
 #[inline]
 fn king_eval(pos: &BoardState) -> isize {
+    let table = if pos.is_endgame() {
+        &WHITE_KING_ENDGAME
+    } else {
+        &WHITE_KING_OPENING
+    };
+
     let mut white_score: isize = 0;
     let white_pawns = pos.bb(Color::White, PieceType::King);
     for (square, _) in white_pawns.iter() {
-        white_score += WHITE_KING_OPENING[square as usize];
+        white_score += table[square as usize];
     }
 
     let mut black_score: isize = 0;
     let black_pawns = pos.bb(Color::Black, PieceType::King);
     for (square, _) in black_pawns.iter() {
-        black_score += WHITE_KING_OPENING[63 - square as usize];
+        black_score += table[63 - square as usize];
     }
 
-    match pos.active_player {
-        Color::Black => black_score - white_score,
-        Color::White => white_score - black_score,
+    white_score - black_score
+}
+
+/// Penalizes a king whose file, or either neighbouring file, has no friendly pawns, since such
+/// files let enemy rooks and queens attack the king. Open files are penalized more than
+/// semi-open ones. King safety matters less as pieces come off, so the penalty shrinks with the
+/// phase of the game.
+#[inline]
+fn king_file_eval(pos: &BoardState) -> isize {
+    let penalty = |color: Color| -> isize {
+        let friendly_pawns = pos.bb(color, PieceType::Pawn);
+        let enemy_pawns = pos.bb(!color, PieceType::Pawn);
+        let file = match pos.king_square(color) {
+            Some(king) => square_to_file(king),
+            None => return 0,
+        };
+
+        let mut penalty = 0;
+        for f in file.saturating_sub(1)..=(file + 1).min(7) {
+            let mask = file_mask(f);
+            if !(mask & friendly_pawns).is_empty() {
+                continue;
+            }
+            penalty += if (mask & enemy_pawns).is_empty() {
+                KING_OPEN_FILE_PENALTY
+            } else {
+                KING_SEMI_OPEN_FILE_PENALTY
+            };
+        }
+        penalty
+    };
+
+    let score = penalty(Color::Black) - penalty(Color::White);
+    score * phase(pos) / MAX_PHASE
+}
+
+/// Penalizes knights and bishops which have not left their starting squares, along with d and e
+/// pawns which are still on their starting squares but blocked, shutting in the bishop behind
+/// them. Development only matters in the opening, so the penalty shrinks with the phase of the
+/// game.
+#[inline]
+fn development_eval(pos: &BoardState) -> isize {
+    let score = development_penalty(pos, Color::Black) - development_penalty(pos, Color::White);
+    score * phase(pos) / MAX_PHASE
+}
+
+fn development_penalty(pos: &BoardState, color: Color) -> isize {
+    let back_rank = color.back_rank();
+    let pawn_rank = back_rank.shift(color.pawn_push_offset());
+    let front_rank = pawn_rank.shift(color.pawn_push_offset());
+    let home = (MINOR_HOME_SQUARES | MINOR_HOME_SQUARES << 56) & back_rank;
+    let minors = pos.bb(color, PieceType::Knight) | pos.bb(color, PieceType::Bishop);
+    let mut penalty = (minors & home).popcount() as isize * UNDEVELOPED_MINOR_PENALTY;
+
+    // The d pawn shuts in the queen's bishop, and the e pawn the king's bishop.
+    for &(pawn_file, bishop_file) in &[(FILED, FILEC), (FILEE, FILEF)] {
+        if pos.bb(color, PieceType::Pawn) & pawn_rank & pawn_file != 0
+            && pos.bb_all() & front_rank & pawn_file != 0
+            && pos.bb(color, PieceType::Bishop) & back_rank & bishop_file != 0
+        {
+            penalty += BLOCKED_CENTER_PAWN_PENALTY;
+        }
     }
+    penalty
 }
 
 #[cfg(test)]
@@ -222,7 +600,129 @@ mod test {
     fn starting_position_equal_evaluation() {
         let pos = BoardState::default();
         let eval = eval(&pos);
-        assert_eq!(eval, 0);
+        assert_eq!(eval, TEMPO_VALUE);
+    }
+
+    #[test]
+    fn evaluation_is_unchanged_by_single_perspective_flip() {
+        // Evaluations of these positions from before the terms were made white-relative.
+        let expected = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                10,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                -15,
+            ),
+            (
+                "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+                -23,
+            ),
+            (
+                "r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8",
+                -359,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+                -40,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", -5),
+            ("4k3/8/8/8/8/8/P7/2B1K3 b - - 0 1", -433),
+            ("3r2k1/5ppp/8/8/8/8/1R3PPP/6K1 b - - 0 1", -9),
+            ("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1", 847),
+            ("8/8/4k3/8/8/3K4/3P4/3R1r2 w - - 0 1", 13),
+        ];
+        for (fen, score) in expected.iter() {
+            assert_eq!(eval(&parse_fen(fen).unwrap()), *score, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn rewards_advanced_passed_pawns() {
+        // The e pawn is passed, while the a pawn is held back by the b pawn on the adjacent file.
+        let pos = parse_fen("7k/1p6/8/8/P3P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(passed_pawns_eval(&pos, Color::White), PASSED_PAWN_VALUES[3]);
+        assert_eq!(passed_pawns_eval(&pos, Color::Black), 0);
+
+        // Only the front pawn of a doubled pair counts.
+        let doubled = parse_fen("7k/8/8/4P3/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            passed_pawns_eval(&doubled, Color::White),
+            PASSED_PAWN_VALUES[4]
+        );
+
+        // A pawn blockaded by the opposing king is not rewarded.
+        let blockaded = parse_fen("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(passed_pawns_eval(&blockaded, Color::White), 0);
+    }
+
+    #[test]
+    fn bishop_behind_own_pawn_chain_is_bad() {
+        // The pawns on c3, d4, and e5 all stand on dark squares.
+        let bad = parse_fen("4k3/8/8/4P3/3P4/2P5/8/2B1K3 w - - 0 1").unwrap();
+        let good = parse_fen("4k3/8/8/4P3/3P4/2P5/8/4KB2 w - - 0 1").unwrap();
+        assert_eq!(
+            bishop_pawns_eval(&bad, Color::White),
+            -3 * BAD_BISHOP_PAWN_PENALTY
+        );
+        assert_eq!(bishop_pawns_eval(&good, Color::White), GOOD_BISHOP_VALUE);
+        assert_eq!(bishop_pawns_eval(&good, Color::Black), 0);
+    }
+
+    #[test]
+    fn breakdown_adds_up_to_evaluation() {
+        let fens = [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "4k3/8/8/8/8/8/P7/2B1K3 b - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        for fen in fens.iter() {
+            let pos = parse_fen(fen).unwrap();
+            let terms = eval_breakdown(&pos);
+            let (scale, terms) = terms.split_last().unwrap();
+            let score: isize = terms.iter().map(|(_, term)| term).sum();
+            assert_eq!(score * scale.1 / SCALE_NORMAL, eval(&pos), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn side_to_move_gets_tempo() {
+        let white_to_move_pos =
+            parse_fen("r3k2r/ppp2ppp/8/3pp3/3PP3/8/PPP2PPP/R3K2R w KQkq - 0 1").unwrap();
+        let black_to_move_pos =
+            parse_fen("r3k2r/ppp2ppp/8/3pp3/3PP3/8/PPP2PPP/R3K2R b KQkq - 0 1").unwrap();
+
+        // Evaluations are relative to the active player, so negate black's to compare from
+        // white's perspective.
+        let white_eval = eval(&white_to_move_pos);
+        let black_eval = -eval(&black_to_move_pos);
+        let tempo = TEMPO_VALUE * 8 / MAX_PHASE;
+
+        assert_eq!(tempo_eval(&white_to_move_pos), tempo);
+        assert_eq!(white_eval - black_eval, 2 * tempo);
+    }
+
+    #[test]
+    fn rook_is_worth_more_in_pawnless_endgame() {
+        let endgame = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(material_eval(&endgame) > PieceType::Rook.material_value());
+
+        // With the rest of the pieces on the board, a rook is worth close to its opening value.
+        let opening = parse_fen("1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1").unwrap();
+        assert!(material_eval(&opening) < material_eval(&endgame));
+        assert!(material_eval(&opening) - PieceType::Rook.material_value() < 10);
+    }
+
+    #[test]
+    fn kings_pawn_opening_gains_space() {
+        let pos = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(space_eval(&BoardState::default()), 0);
+
+        // Terms are scored from white's point of view, even with black to move.
+        let score = space_eval(&pos);
+        assert!(score > 0);
+        assert!(score < PieceType::Pawn.material_value() / 2);
     }
 
     #[test]
@@ -232,6 +732,118 @@ mod test {
         assert!(eval < 400);
     }
 
+    #[test]
+    fn king_prefers_center_in_endgame() {
+        let center = parse_fen("k7/8/8/8/4K3/8/8/8 w - - 0 1").unwrap();
+        let back_rank = parse_fen("k7/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(king_eval(&center) > king_eval(&back_rank));
+    }
+
+    #[test]
+    fn king_prefers_back_rank_in_middlegame() {
+        let back_rank = BoardState::default();
+        let center = parse_fen("rnbqkbnr/pppppppp/8/8/8/4K3/PPPPPPPP/RNBQ1BNR w kq - 0 1").unwrap();
+        assert!(king_eval(&back_rank) > king_eval(&center));
+    }
+
+    #[test]
+    fn rewards_queen_on_open_file() {
+        let pos = parse_fen("3qk3/ppp2ppp/8/8/8/8/PPPP1PPP/4QK2 w - - 0 1").unwrap();
+        let score = open_file_eval(
+            &pos,
+            PieceType::Queen,
+            QUEEN_OPEN_FILE_VALUE,
+            QUEEN_SEMI_OPEN_FILE_VALUE,
+        );
+        assert_eq!(score, QUEEN_OPEN_FILE_VALUE - QUEEN_SEMI_OPEN_FILE_VALUE);
+    }
+
+    #[test]
+    fn rewards_coordinated_rooks() {
+        let doubled = parse_fen("4k3/pp3ppp/8/8/8/8/PP1R1PPP/3RK3 w - - 0 1").unwrap();
+        let separate = parse_fen("4k3/pp3ppp/8/8/8/8/PP2RPPP/3RK3 w - - 0 1").unwrap();
+        let blocked = parse_fen("4k3/pp3ppp/8/8/8/3R4/PP1B1PPP/3RK3 w - - 0 1").unwrap();
+        let connected = parse_fen("4k3/pp3ppp/8/8/8/8/PP3PPP/R2R2K1 w - - 0 1").unwrap();
+        assert_eq!(
+            rook_coordination(&doubled, Color::White),
+            DOUBLED_ROOKS_VALUE
+        );
+        assert_eq!(rook_coordination(&separate, Color::White), 0);
+        assert_eq!(rook_coordination(&blocked, Color::White), 0);
+        assert_eq!(
+            rook_coordination(&connected, Color::White),
+            CONNECTED_ROOKS_VALUE
+        );
+        assert!(rook_eval(&doubled) > rook_eval(&separate));
+    }
+
+    #[test]
+    fn opening_file_in_front_of_castled_king_is_penalized() {
+        let closed =
+            parse_fen("r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 0 1")
+                .unwrap();
+        let traded =
+            parse_fen("r1bq1rk1/ppp2ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1P1P/R1BQ1RK1 w - - 0 1")
+                .unwrap();
+
+        assert_eq!(king_file_eval(&closed), 0);
+        assert!(king_file_eval(&traded) < 0);
+        assert!(eval(&traded) < eval(&closed));
+    }
+
+    #[test]
+    fn developing_pieces_reduces_penalty() {
+        let start = BoardState::default();
+        let knight_out =
+            parse_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1").unwrap();
+        assert_eq!(
+            development_penalty(&start, Color::White),
+            4 * UNDEVELOPED_MINOR_PENALTY
+        );
+        assert!(
+            development_penalty(&knight_out, Color::White)
+                < development_penalty(&start, Color::White)
+        );
+        assert_eq!(
+            development_penalty(&knight_out, Color::Black),
+            development_penalty(&start, Color::Black)
+        );
+
+        // The knight on d3 blocks the d pawn, which shuts in the bishop on c1.
+        let blocked =
+            parse_fen("rnbqkbnr/pppppppp/8/8/8/3N4/PPPPPPPP/R1BQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(
+            development_penalty(&blocked, Color::White),
+            3 * UNDEVELOPED_MINOR_PENALTY + BLOCKED_CENTER_PAWN_PENALTY
+        );
+    }
+
+    #[test]
+    fn wrong_rook_pawn_is_drawn() {
+        let wrong_bishop = parse_fen("k7/8/8/8/8/8/P7/2B1K3 w - - 0 1").unwrap();
+        let right_bishop = parse_fen("k7/8/8/8/8/8/P7/3BK3 w - - 0 1").unwrap();
+        let king_far_away = parse_fen("8/8/8/4k3/8/8/P7/2B1K3 w - - 0 1").unwrap();
+        let black_pawns = parse_fen("4kb2/8/8/8/8/7p/7p/7K b - - 0 1").unwrap();
+
+        assert!(is_wrong_rook_pawn(&wrong_bishop));
+        assert!(!is_wrong_rook_pawn(&right_bishop));
+        assert!(!is_wrong_rook_pawn(&king_far_away));
+        assert!(is_wrong_rook_pawn(&black_pawns));
+        assert_eq!(eval(&wrong_bishop), 0);
+        assert!(eval(&right_bishop) >= PieceType::Bishop.material_value());
+    }
+
+    #[test]
+    fn philidor_position_is_scaled_towards_draw() {
+        let philidor = parse_fen("4k3/8/3r4/4PK2/8/8/8/1R6 b - - 0 1").unwrap();
+        let king_cut_off = parse_fen("k7/8/3r4/4PK2/8/8/8/1R6 b - - 0 1").unwrap();
+
+        assert!(is_drawn_rook_pawn_endgame(&philidor));
+        assert!(!is_drawn_rook_pawn_endgame(&king_cut_off));
+        assert!(eval(&philidor).abs() < PieceType::Pawn.material_value() / 4);
+        assert!(eval(&king_cut_off).abs() >= PieceType::Pawn.material_value() / 2);
+    }
+
     #[test]
     fn should_give_equal_evals_for_relative_color() {
         // Since the evaluation function is relative to the current player, flipping the player to move should give
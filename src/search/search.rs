@@ -1,11 +1,43 @@
 use crate::board::BoardState;
-use crate::chess_move::EvaledMove;
+use crate::chess_move::{EvaledMove, Move, MoveType};
 use crate::search::stats::Stats;
 
+/// Everything learned from a search: the move to play, its score relative to the player to move,
+/// the deepest completed iteration, the number of nodes searched, and the principal variation.
+/// The principal variation always starts with the best move, unless the position has none.
+#[allow(dead_code)]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: isize,
+    pub depth: usize,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
 pub trait Searcher {
     fn new() -> Self;
     fn stats(&self) -> &Stats;
     fn best_move(&mut self, pos: &mut BoardState) -> EvaledMove;
     fn best_move_depth(&mut self, pos: &mut BoardState, depth: usize) -> EvaledMove;
     fn move_time(&mut self, seconds: u128);
+
+    /// Search the position to the given depth and return the full result. Searchers which do not
+    /// keep track of a principal variation report only the best move, and the requested depth.
+    #[allow(dead_code)]
+    fn search(&mut self, pos: &mut BoardState, depth: usize) -> SearchResult {
+        let best = self.best_move_depth(pos, depth);
+        let pv = if best.mv.kind == MoveType::Null {
+            Vec::new()
+        } else {
+            vec![best.mv]
+        };
+        SearchResult {
+            best_move: best.mv,
+            score: best.eval,
+            depth,
+            nodes: self.stats().nodes as u64,
+            pv,
+        }
+    }
 }
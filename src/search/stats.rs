@@ -1,22 +1,41 @@
+use std::time::Instant;
+
 pub struct Stats {
     pub nodes: usize,
     leaf_nodes: usize,
+    start: Instant,
 }
 
 impl Stats {
+    #[must_use]
     pub fn new() -> Stats {
         Stats {
             nodes: 0,
             leaf_nodes: 0,
+            start: Instant::now(),
         }
     }
 
     pub fn reset(&mut self) {
         self.nodes = 0;
         self.leaf_nodes = 0;
+        self.start = Instant::now();
     }
 
     pub fn count_node(&mut self) {
         self.nodes += 1;
     }
+
+    /// Returns the number of nodes counted per second since the statistics were last reset.
+    #[must_use]
+    pub fn nps(&self) -> u128 {
+        let elapsed = self.start.elapsed().as_millis().max(1);
+        self.nodes as u128 * 1000 / elapsed
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,53 @@
+use crate::board::BoardState;
+use crate::search::eval::MATE_VALUE;
+
+/// The score of a position which a tablebase reports as won, before subtracting the ply at which
+/// it was reached. It is far below `MATE_VALUE`, so that a mate found by the search is always
+/// preferred, and far above any static evaluation.
+pub const TABLEBASE_WIN: isize = MATE_VALUE - 1000;
+
+/// The result of a position under perfect play, from the point of view of the player to move.
+#[allow(dead_code)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Wdl {
+    /// Return the score of a position with this result, reached at the given ply from the root.
+    /// Wins which are reached sooner score higher, and losses which are reached later score higher.
+    #[must_use]
+    pub fn score(self, ply: u8) -> isize {
+        match self {
+            Wdl::Win => TABLEBASE_WIN - isize::from(ply),
+            Wdl::Draw => 0,
+            Wdl::Loss => -TABLEBASE_WIN + isize::from(ply),
+        }
+    }
+}
+
+/// An endgame tablebase consulted by the search, such as a Syzygy prober. Positions with at most
+/// `max_pieces` pieces on the board (kings included) are probed below the root; the result of a
+/// root move is therefore found by probing the position after it.
+pub trait Tablebase: Send + Sync {
+    /// Return the largest number of pieces of any position held by the tablebase.
+    fn max_pieces(&self) -> u32;
+
+    /// Return the result of the given position, or `None` if it is not held by the tablebase.
+    fn probe_wdl(&self, pos: &BoardState) -> Option<Wdl>;
+}
+
+/// A tablebase which holds no positions, used by the search until another one is set.
+pub struct NoTablebase;
+
+impl Tablebase for NoTablebase {
+    fn max_pieces(&self) -> u32 {
+        0
+    }
+
+    fn probe_wdl(&self, _pos: &BoardState) -> Option<Wdl> {
+        None
+    }
+}
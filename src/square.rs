@@ -76,28 +76,27 @@ pub fn square_to_file(s: Square) -> u8 {
     s % 8
 }
 
-#[allow(dead_code)]
 pub fn square_to_rank(s: Square) -> u8 {
-    s % 8
+    s / 8
+}
+
+/// Parse a square from its algebraic name, such as `e4`. Returns `None` unless the input is
+/// exactly a file letter from `a` to `h` followed by a rank digit from `1` to `8`.
+pub fn square_from_algebraic(alg: &str) -> Option<Square> {
+    match alg.as_bytes() {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+            Some(rank_file_to_index(rank - b'1', file - b'a'))
+        }
+        _ => None,
+    }
 }
 
-pub fn algebraic_to_square(alg: &str) -> Square {
-    let mut s = alg.chars();
-    let file = s.next().unwrap();
-    let rank = s.next().unwrap();
-    let file = match file as char {
-        'a' => 0,
-        'b' => 1,
-        'c' => 2,
-        'd' => 3,
-        'e' => 4,
-        'f' => 5,
-        'g' => 6,
-        'h' => 7,
-        _ => 0,
-    };
-    let rank = char::to_digit(rank, 10).unwrap() as u8;
-    rank_file_to_index(rank - 1, file)
+/// Returns the algebraic name of the given square, such as `e4`.
+#[allow(dead_code)]
+pub fn square_to_algebraic(s: Square) -> String {
+    let file = (b'a' + square_to_file(s)) as char;
+    let rank = (b'1' + square_to_rank(s)) as char;
+    format!("{file}{rank}")
 }
 
 #[cfg(test)]
@@ -120,19 +119,42 @@ mod tests {
 
     #[test]
     fn converts_e4_to_square() {
-        let index = algebraic_to_square("e4");
+        let index = square_from_algebraic("e4").unwrap();
         assert_eq!(index, 28);
     }
 
+    #[test]
+    fn parses_only_valid_algebraic_squares() {
+        assert_eq!(square_from_algebraic("e4"), Some(SquareIndex::E4 as Square));
+        assert_eq!(square_from_algebraic("a1"), Some(SquareIndex::A1 as Square));
+        assert_eq!(square_from_algebraic("h8"), Some(SquareIndex::H8 as Square));
+        for invalid in ["", "e", "e9", "e0", "i4", "E4", "4e", "e44", "-", "é4"].iter() {
+            assert_eq!(square_from_algebraic(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn converts_square_to_algebraic() {
+        assert_eq!(square_to_algebraic(SquareIndex::E4 as Square), "e4");
+        assert_eq!(square_to_algebraic(SquareIndex::A8 as Square), "a8");
+        assert_eq!(square_to_algebraic(SquareIndex::H1 as Square), "h1");
+    }
+
+    #[test]
+    fn converts_e4_to_rank() {
+        assert_eq!(square_to_rank(square_from_algebraic("e4").unwrap()), 3);
+        assert_eq!(square_to_rank(square_from_algebraic("h8").unwrap()), 7);
+    }
+
     #[test]
     fn converts_a8_to_square() {
-        let index = algebraic_to_square("a8");
+        let index = square_from_algebraic("a8").unwrap();
         assert_eq!(index, 56);
     }
 
     #[test]
     fn converts_a4_to_file() {
-        let square = algebraic_to_square("a4");
+        let square = square_from_algebraic("a4").unwrap();
         println!("{}", square);
         let file = square_to_file(square);
         assert_eq!(file, 0);
@@ -140,7 +162,7 @@ mod tests {
 
     #[test]
     fn converts_b4_to_file() {
-        let square = algebraic_to_square("b4");
+        let square = square_from_algebraic("b4").unwrap();
         println!("{}", square);
         let file = square_to_file(square);
         assert_eq!(file, 1);
@@ -1,9 +1,10 @@
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::mem;
 
 use itertools::Itertools;
-use rand::prelude::ThreadRng;
-use rand::RngCore;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use crate::bitboard::{Bitboard, PieceItr};
 use crate::board::BoardState;
@@ -13,6 +14,10 @@ use crate::square::square_to_file;
 
 pub type ZobristHash = u64;
 
+/// The seed of the keys used to hash positions both in games and in the search, so that the
+/// hashes returned by `Game::zobrist_history` are the same in every run, and so is every search.
+pub const ZOBRIST_SEED: u64 = 0x7075_7270_6c65;
+
 /// A `ZobristTable` maintains the random values needed to create Zobrist hashes
 /// for use in a transposition table.
 pub struct ZobristTable {
@@ -24,9 +29,22 @@ pub struct ZobristTable {
 
 /// A `ZobristTable` manages the randomly generated `ZobristHashes` for a given session
 impl ZobristTable {
+    #[allow(dead_code)]
     pub fn init() -> ZobristTable {
-        let mut rng = rand::thread_rng();
+        ZobristTable::from_rng(&mut rand::thread_rng())
+    }
+
+    /// Construct a table whose keys are generated from the given seed. Tables built from the same
+    /// seed hash every position identically, even across separate runs, so hashes from such a
+    /// table are suitable for persisting. The keys come from `ChaCha8`, whose output is fixed for a
+    /// given seed on every platform and version, unlike that of `StdRng`.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn from_seed(seed: u64) -> ZobristTable {
+        ZobristTable::from_rng(&mut ChaCha8Rng::seed_from_u64(seed))
+    }
 
+    fn from_rng<R: RngCore>(rng: &mut R) -> ZobristTable {
         let len = 2 * 6 * 64;
         let mut table: [u64; 2 * 64 * 6] = [0; 2 * 6 * 64];
         for i in 0..len {
@@ -34,8 +52,8 @@ impl ZobristTable {
         }
 
         let whites_turn = rng.next_u64();
-        let castling_rights = ZobristTable::gen_castling(&mut rng);
-        let en_passant_file = ZobristTable::gen_enpassant(&mut rng);
+        let castling_rights = ZobristTable::gen_castling(rng);
+        let en_passant_file = ZobristTable::gen_enpassant(rng);
 
         ZobristTable {
             table,
@@ -45,7 +63,7 @@ impl ZobristTable {
         }
     }
 
-    fn gen_castling(rng: &mut ThreadRng) -> [ZobristHash; 4] {
+    fn gen_castling<R: RngCore>(rng: &mut R) -> [ZobristHash; 4] {
         let mut table = [0u64; 4];
         for i in 0..4 {
             table[i] = rng.next_u64();
@@ -53,7 +71,7 @@ impl ZobristTable {
         table
     }
 
-    fn gen_enpassant(rng: &mut ThreadRng) -> [ZobristHash; 8] {
+    fn gen_enpassant<R: RngCore>(rng: &mut R) -> [ZobristHash; 8] {
         let mut table = [0u64; 8];
         for i in 0..8 {
             table[i] = rng.next_u64();
@@ -61,27 +79,58 @@ impl ZobristTable {
         table
     }
 
+    /// Hash the placement of the pieces, the player to move, the castling rights and the en
+    /// passant square. The move counters are left out, so positions which only differ in them
+    /// count as the same position both for repetitions and for the transposition table.
     pub fn hash(&self, pos: &mut BoardState) -> ZobristHash {
         let mut hash: ZobristHash = 0;
         for (piece, color) in PieceType::iterator().cartesian_product(Color::iterator()) {
-            let bb: Bitboard = pos.bb(*color, *piece);
-            let i = match *piece {
-                PieceType::Pawn => 0,
-                PieceType::Rook => 1,
-                PieceType::Knight => 2,
-                PieceType::Bishop => 3,
-                PieceType::Queen => 4,
-                PieceType::King => 5,
-            };
-            for (j, _) in bb.iter() {
-                let index = match color {
-                    Color::White => (i * 64) + j as usize,
-                    Color::Black => (i * 64) + j as usize + 384_usize,
-                };
-                hash ^= self.table[index];
+            for square in pos.bb(*color, *piece).squares() {
+                hash ^= self.piece_key(*piece, *color, square);
             }
         }
+        hash ^ self.state_key(pos)
+    }
 
+    /// Return the hash of `after` given the hash of `before`, by only updating the keys of the
+    /// pieces, castling rights, en passant square and player to move which differ between the two
+    /// positions. After a single move this is much cheaper than hashing `after` from scratch.
+    #[must_use]
+    pub fn hash_after(
+        &self,
+        hash: ZobristHash,
+        before: &BoardState,
+        after: &BoardState,
+    ) -> ZobristHash {
+        let mut hash = hash ^ self.state_key(before) ^ self.state_key(after);
+        for (piece, color) in PieceType::iterator().cartesian_product(Color::iterator()) {
+            let changed: Bitboard = before.bb(*color, *piece) ^ after.bb(*color, *piece);
+            for square in changed.squares() {
+                hash ^= self.piece_key(*piece, *color, square);
+            }
+        }
+        hash
+    }
+
+    fn piece_key(&self, piece: PieceType, color: Color, square: u8) -> ZobristHash {
+        let i = match piece {
+            PieceType::Pawn => 0,
+            PieceType::Rook => 1,
+            PieceType::Knight => 2,
+            PieceType::Bishop => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+        };
+        let index = match color {
+            Color::White => (i * 64) + square as usize,
+            Color::Black => (i * 64) + square as usize + 384_usize,
+        };
+        self.table[index]
+    }
+
+    /// Return the part of the hash which does not depend on the placement of the pieces.
+    fn state_key(&self, pos: &BoardState) -> ZobristHash {
+        let mut hash: ZobristHash = 0;
         if pos.castling_rights.black_king {
             hash ^= self.castling_rights[0];
         }
@@ -126,6 +175,9 @@ pub enum Bound {
     Exact,
 }
 
+/// The number of slots at the start of the table which are checked to estimate how full it is.
+const USAGE_SAMPLE: usize = 1000;
+
 /// A transposition table is a lightweight hash map which maps Zobrist hashes (u64s) to entries.
 pub struct TranspositionTable {
     table: Vec<Option<Entry>>,
@@ -164,12 +216,29 @@ impl TranspositionTable {
         false
     }
 
+    /// Remove every entry from the table, keeping its size.
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+    }
+
     /// Using the given hash, return the Entry which is associated with it in the table.
     pub fn get(&self, hash: u64) -> Option<Entry> {
         let index = hash as usize % self.table.len();
         self.table[index]
     }
 
+    /// Estimate how full the table is in parts per thousand, as reported by UCI `hashfull`.
+    /// Hashes spread entries evenly over the table, so only the first thousand slots are checked.
+    #[must_use]
+    pub fn usage_permille(&self) -> u16 {
+        let sample = self.table.len().min(USAGE_SAMPLE);
+        if sample == 0 {
+            return 0;
+        }
+        let used = self.table[..sample].iter().filter(|e| e.is_some()).count();
+        u16::try_from(used * 1000 / sample).unwrap_or(1000)
+    }
+
     /// Return the principal variation, starting with the given position
     #[allow(dead_code)]
     pub fn pv(&self, pos: &mut BoardState, zobrist: &ZobristTable) -> Vec<EvaledMove> {
@@ -192,7 +261,7 @@ impl TranspositionTable {
         let mv = self.get(hash);
 
         if let Some(m) = mv {
-            if m.best_move.mv.kind == MoveType::Null {
+            if m.hash != hash || m.best_move.mv.kind == MoveType::Null {
                 return;
             }
             pv.push(m.best_move);
@@ -205,11 +274,67 @@ impl TranspositionTable {
     }
 }
 
+/// A cache of static evaluations, so that positions reached again during a search, such as the
+/// leaves of quiescence search, are not evaluated from scratch. The full hash is stored alongside
+/// each evaluation to tell apart positions which share a slot.
+pub struct EvalCache {
+    table: Vec<Option<(ZobristHash, isize)>>,
+    hits: usize,
+}
+
+impl EvalCache {
+    /// Constructs a new `EvalCache` with the given number of entries
+    pub fn new(size: usize) -> EvalCache {
+        EvalCache {
+            table: vec![None; size],
+            hits: 0,
+        }
+    }
+
+    /// Return the cached evaluation of the position with the given hash, if there is one.
+    pub fn get(&mut self, hash: ZobristHash) -> Option<isize> {
+        match self.table[self.index(hash)] {
+            Some((key, eval)) if key == hash => {
+                self.hits += 1;
+                Some(eval)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the number of lookups which found their position in the cache since it was created
+    /// or last cleared.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Cache the evaluation of the position with the given hash, replacing whichever position
+    /// held its slot before.
+    pub fn save(&mut self, hash: ZobristHash, eval: isize) {
+        let index = self.index(hash);
+        self.table[index] = Some((hash, eval));
+    }
+
+    fn index(&self, hash: ZobristHash) -> usize {
+        usize::try_from(hash % self.table.len() as u64).unwrap_or_default()
+    }
+
+    /// Remove every evaluation from the cache, keeping its size.
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+        self.hits = 0;
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::board::Castle;
     use crate::chess_move::EvaledMove;
     use crate::fen::parse_fen;
-    use crate::table::{Bound, Entry, TranspositionTable, ZobristTable};
+    use crate::move_gen::MoveGenerator;
+    use crate::table::{Bound, Entry, EvalCache, TranspositionTable, ZobristTable, USAGE_SAMPLE};
 
     #[test]
     fn same_position_should_have_same_hash() {
@@ -224,6 +349,49 @@ mod test {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn same_seed_should_give_same_hash() {
+        let zobrist1 = ZobristTable::from_seed(42);
+        let zobrist2 = ZobristTable::from_seed(42);
+        let zobrist3 = ZobristTable::from_seed(43);
+
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+
+        assert_eq!(zobrist1.hash(&mut pos), zobrist2.hash(&mut pos));
+        assert_ne!(zobrist1.hash(&mut pos), zobrist3.hash(&mut pos));
+        // Seeded keys must not change between versions, or persisted hashes would be invalidated.
+        assert_eq!(zobrist1.hash(&mut pos), 0xb603_97fb_091f_6eef);
+    }
+
+    #[test]
+    fn each_castling_right_changes_hash() {
+        let zobrist = ZobristTable::init();
+        let mut pos = parse_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+        let original = zobrist.hash(&mut pos);
+
+        let mut seen = vec![original];
+        for i in 0..4 {
+            let toggle = |rights: &mut Castle| match i {
+                0 => rights.white_king = !rights.white_king,
+                1 => rights.white_queen = !rights.white_queen,
+                2 => rights.black_king = !rights.black_king,
+                _ => rights.black_queen = !rights.black_queen,
+            };
+
+            toggle(&mut pos.castling_rights);
+            let toggled = zobrist.hash(&mut pos);
+            assert!(!seen.contains(&toggled));
+            seen.push(toggled);
+
+            toggle(&mut pos.castling_rights);
+            assert_eq!(zobrist.hash(&mut pos), original);
+        }
+
+        pos.castling_rights = Castle::none();
+        assert!(!seen.contains(&zobrist.hash(&mut pos)));
+    }
+
     #[test]
     fn different_positions_should_have_different_hashes() {
         let zobrist = ZobristTable::init();
@@ -251,6 +419,15 @@ mod test {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn move_counters_do_not_change_hash() {
+        let zobrist = ZobristTable::init();
+
+        let mut pos1 = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut pos2 = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(zobrist.hash(&mut pos1), zobrist.hash(&mut pos2));
+    }
+
     #[test]
     fn different_positions_should_be_different() {
         let zobrist = ZobristTable::init();
@@ -348,4 +525,70 @@ mod test {
         assert_eq!(fetched_entry.is_some(), true);
         assert_eq!(fetched_entry.unwrap(), entry_one);
     }
+
+    #[test]
+    fn usage_counts_sampled_slots() {
+        let mut table = TranspositionTable::new(2 * USAGE_SAMPLE);
+        assert_eq!(table.usage_permille(), 0);
+
+        let entry = Entry {
+            best_move: EvaledMove::null(0),
+            hash: 0,
+            depth: 0,
+            bound: Bound::Exact,
+        };
+        for hash in 0..USAGE_SAMPLE as u64 / 2 {
+            table.save(hash, entry);
+        }
+        assert_eq!(table.usage_permille(), 500);
+
+        for hash in 0..USAGE_SAMPLE as u64 {
+            table.save(hash, entry);
+        }
+        assert_eq!(table.usage_permille(), 1000);
+
+        table.clear();
+        assert_eq!(table.usage_permille(), 0);
+        assert_eq!(table.get(0), None);
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        let zobrist = ZobristTable::init();
+        let gen = MoveGenerator::new();
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R b KQkq a3 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        for fen in fens.iter() {
+            let mut pos = parse_fen(fen).unwrap();
+            let hash = zobrist.hash(&mut pos);
+            for mv in gen.all_moves(&pos) {
+                let mut new_pos = pos.clone_with_move(mv);
+                assert_eq!(
+                    zobrist.hash_after(hash, &pos, &new_pos),
+                    zobrist.hash(&mut new_pos),
+                    "{fen} {}",
+                    mv.to_algebraic()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn eval_cache_checks_full_hash() {
+        let mut cache = EvalCache::new(16);
+        cache.save(3, 42);
+        assert_eq!(cache.get(3), Some(42));
+        // Shares the slot of the first hash, but is a different position.
+        assert_eq!(cache.get(19), None);
+
+        cache.save(19, -7);
+        assert_eq!(cache.get(19), Some(-7));
+        assert_eq!(cache.get(3), None);
+
+        cache.clear();
+        assert_eq!(cache.get(19), None);
+    }
 }
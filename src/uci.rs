@@ -1,45 +1,292 @@
-use std::io::{stdin, BufRead};
+use std::convert::TryFrom;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 use itertools::Itertools;
 
 use crate::board::BoardState;
+use crate::chess_move::EvaledMove;
+use crate::clock::{Clock, TimeControl};
 use crate::fen::parse_fen;
 use crate::move_gen::MoveGenerator;
+use crate::piece::Color;
 use crate::search::alpha_beta::AlphaBeta;
 use crate::search::eval::eval;
 use crate::search::search::Searcher;
 
+const MAX_DEPTH: usize = 15;
+
+/// The reply to `uci`, which identifies the engine and lists its options.
+const UCI_ID: [&str; 4] = [
+    "id name Purple",
+    "id author Joshua L Ervin",
+    "option name UseHashTable type check default true",
+    "uciok",
+];
+
+/// The depth used for `go infinite`, which is deep enough that the search will always be stopped
+/// before it is reached.
+const INFINITE_DEPTH: usize = 100;
+
 pub fn uci_loop() {
+    run(stdin().lock(), &mut stdout());
+}
+
+/// Read UCI commands from the given input until `quit` or the end of the input. The replies to
+/// `uci` and `isready` are written to the given output as soon as the command is read, even while
+/// a search is running in the background, while search results are printed to stdout. The
+/// expensive lookup tables are built once up front, and shared by every later command.
+fn run(input: impl BufRead, out: &mut impl Write) {
     let mut pos = BoardState::default();
     let mut searcher = AlphaBeta::new();
-    loop {
-        let mut buffer = String::new();
-        stdin().lock().read_line(&mut buffer).unwrap();
-        let key = buffer.split_ascii_whitespace().collect_vec();
-        match &(*key.first().unwrap()).to_string()[..] {
+    searcher.progress_info(true);
+    let mut searcher = Some(searcher);
+    let mut background: Option<BackgroundSearch> = None;
+    for line in input.lines().map_while(Result::ok) {
+        let key = line.split_ascii_whitespace().collect_vec();
+        let Some(&command) = key.first() else {
+            continue;
+        };
+
+        // While pondering or analysing, only a handful of commands leave the search running.
+        // Anything else means the opponent did not play the expected move, so the search is
+        // discarded.
+        if let Some(p) = background.take() {
+            match command {
+                "ponderhit" => {
+                    searcher = Some(finish_search(&mut pos, p.ponderhit()));
+                    continue;
+                }
+                "stop" => {
+                    searcher = Some(finish_search(&mut pos, p.stop()));
+                    continue;
+                }
+                "isready" => {
+                    reply(out, &["readyok"]);
+                    background = Some(p);
+                    continue;
+                }
+                _ => searcher = Some(p.stop().0),
+            }
+        }
+
+        let gen = &searcher.as_ref().unwrap().gen;
+        match command {
             "quit" => break,
-            "uci" => init_uci(),
-            "position" => pos = update_position(&key[1..].join(" ")),
-            "go" => go(&mut pos, &mut searcher, &key),
-            "isready" => println!("readyok"),
-            "ucinewgame" => pos = update_position(&"startpos".to_string()),
+            "uci" => reply(out, &UCI_ID),
+            "position" => pos = update_position(gen, &key[1..].join(" ")),
+            "go" => background = go(&mut pos, &mut searcher, &key),
+            "isready" => reply(out, &["readyok"]),
+            "setoption" => set_option(out, searcher.as_mut().unwrap(), &key),
+            "debug" => set_debug(out, searcher.as_mut().unwrap(), &key),
+            "ucinewgame" => pos = update_position(gen, &"startpos".to_string()),
+            "stop" | "ponderhit" => {}
             //"movetime" => searcher.move_time(key[1].parse::<u64>().unwrap()),
-            _ => println!("Command not understood"),
+            _ => reply(out, &["Command not understood"]),
+        }
+    }
+}
+
+/// Start a search from the given position. Regular searches are run to completion before
+/// returning, while `go ponder` and `go infinite` searches are started on a background thread and
+/// returned so that the main loop can keep listening for `ponderhit` or `stop`.
+fn go(
+    pos: &mut BoardState,
+    searcher: &mut Option<AlphaBeta>,
+    data: &[&str],
+) -> Option<BackgroundSearch> {
+    let movetime = move_time(pos, data);
+
+    if data.contains(&"infinite") {
+        let searcher = searcher.take().unwrap();
+        return Some(BackgroundSearch::start(
+            searcher,
+            *pos,
+            None,
+            INFINITE_DEPTH,
+        ));
+    }
+
+    if data.contains(&"ponder") {
+        let searcher = searcher.take().unwrap();
+        return Some(BackgroundSearch::start(searcher, *pos, movetime, MAX_DEPTH));
+    }
+
+    let mut s = searcher.take().unwrap();
+    let depth = apply_limits(&mut s, pos, data);
+    let mv = s.best_move_depth(pos, depth);
+    *searcher = Some(finish_search(pos, (s, mv)));
+    None
+}
+
+/// Configure the searcher with the time, `nodes`, and `mate` limits of a `go` command, and return
+/// the depth to search to. This is `MAX_DEPTH` unless limited by `depth`, or by `mate`, which only
+/// needs to search deep enough to measure a mate in the given number of moves.
+fn apply_limits(searcher: &mut AlphaBeta, pos: &BoardState, data: &[&str]) -> usize {
+    match move_time(pos, data) {
+        Some(movetime) => searcher.move_time(movetime),
+        None => searcher.clear_move_time(),
+    }
+    match go_argument(data, "nodes") {
+        Some(nodes) => searcher.node_limit(nodes as usize),
+        None => searcher.clear_node_limit(),
+    }
+    let mate = go_argument(data, "mate").map(|moves| moves as usize);
+    match mate {
+        Some(moves) => searcher.mate_search(moves),
+        None => searcher.clear_mate_search(),
+    }
+    go_argument(data, "depth")
+        .map(|depth| depth as usize)
+        .or_else(|| mate.map(|moves| 2 * moves))
+        .map_or(MAX_DEPTH, |depth| depth.min(MAX_DEPTH))
+}
+
+/// Return the time to spend on the next move, which is either the `movetime` of a `go` command, or
+/// the budget of the side to move given the time left on its clock, its increment, and the number
+/// of moves until the next time control.
+fn move_time(pos: &BoardState, data: &[&str]) -> Option<u128> {
+    if let Some(movetime) = go_argument(data, "movetime") {
+        return Some(movetime);
+    }
+
+    let (time, inc) = match pos.active_player {
+        Color::White => ("wtime", "winc"),
+        Color::Black => ("btime", "binc"),
+    };
+    let clock = Clock::new(TimeControl {
+        base: go_argument(data, time)?,
+        increment: go_argument(data, inc).unwrap_or(0),
+        moves_to_go: go_argument(data, "movestogo").and_then(|moves| u32::try_from(moves).ok()),
+    });
+    Some(clock.budget())
+}
+
+/// Return the value following the given keyword in a `go` command, if present. A value which is
+/// not a number is ignored, as if the keyword was missing.
+fn go_argument(data: &[&str], keyword: &str) -> Option<u128> {
+    data.iter()
+        .position(|x| *x == keyword)
+        .and_then(|i| data.get(i + 1))
+        .and_then(|x| x.parse::<u128>().ok())
+}
+
+/// Apply a `setoption name <name> value <value>` command. The only option is `UseHashTable`,
+/// which turns the transposition table on or off, for example to compare search results with and
+/// without it.
+fn set_option(out: &mut impl Write, searcher: &mut AlphaBeta, data: &[&str]) {
+    let name = data.iter().position(|x| *x == "name");
+    let value = data.iter().position(|x| *x == "value");
+    let (name, value) = match (name, value) {
+        (Some(name), Some(value)) if name < value => {
+            (data[name + 1..value].join(" "), data[value + 1..].join(" "))
+        }
+        _ => {
+            reply(out, &["Command not understood"]);
+            return;
         }
+    };
+
+    match (name.as_str(), value.as_str()) {
+        ("UseHashTable", "true") => searcher.use_table(true),
+        ("UseHashTable", "false") => searcher.use_table(false),
+        _ => reply(out, &[&format!("Unknown option {name} with value {value}")]),
     }
 }
 
-fn go(pos: &mut BoardState, searcher: &mut AlphaBeta, data: &[&str]) {
-    let movetime = data[2].parse::<u128>().unwrap();
-    //searcher.move_time((movetime / 1000) - 1);
-    searcher.move_time(movetime);
-    let mv = searcher.best_move_depth(pos, 15);
-    println!("eval: {}", mv.eval);
-    println!("static eval: {}", eval(pos));
-    println!("bestmove {}", mv.mv.to_algebraic());
+/// Apply a `debug on|off` command, which turns the extra `info string` diagnostics reported
+/// during and after a search on or off.
+fn set_debug(out: &mut impl Write, searcher: &mut AlphaBeta, data: &[&str]) {
+    match data.get(1) {
+        Some(&"on") => searcher.debug(true),
+        Some(&"off") => searcher.debug(false),
+        _ => reply(out, &["Command not understood"]),
+    }
 }
 
-fn update_position(fen: &String) -> BoardState {
+/// Report the result of a finished search, returning the searcher so that it can be reused.
+fn finish_search(pos: &mut BoardState, (searcher, mv): (AlphaBeta, EvaledMove)) -> AlphaBeta {
+    if searcher.is_debug() {
+        println!("info string eval {}", mv.eval);
+        println!("info string static eval {}", eval(pos));
+    }
+    match searcher.ponder_move(pos, mv.mv) {
+        Some(ponder_move) => println!(
+            "bestmove {} ponder {}",
+            mv.mv.to_algebraic(),
+            ponder_move.to_algebraic()
+        ),
+        None => println!("bestmove {}", mv.mv.to_algebraic()),
+    }
+    searcher
+}
+
+/// A search running on a background thread, either while the opponent is thinking or while
+/// analysing until told to stop. The searcher is handed back once the search is finished, along
+/// with its best move, and the thread reports on `finished` as soon as it is done.
+struct BackgroundSearch {
+    handle: JoinHandle<(AlphaBeta, EvaledMove)>,
+    stop: Arc<AtomicBool>,
+    finished: Receiver<()>,
+    move_time: Option<u128>,
+}
+
+impl BackgroundSearch {
+    /// Begin searching the given position to the given depth without a time limit. The provided
+    /// move time is only applied once the opponent plays the expected move.
+    fn start(
+        mut searcher: AlphaBeta,
+        mut pos: BoardState,
+        move_time: Option<u128>,
+        depth: usize,
+    ) -> BackgroundSearch {
+        let stop = searcher.stop_flag();
+        stop.store(false, Ordering::Relaxed);
+        searcher.clear_move_time();
+        searcher.clear_node_limit();
+
+        // The searcher is moved onto the search thread, so `AlphaBeta` must remain `Send`.
+        let (done, finished) = channel();
+        let handle = thread::spawn(move || {
+            let mv = searcher.best_move_depth(&mut pos, depth);
+            // Nobody is listening any more if the search was stopped instead.
+            done.send(()).ok();
+            (searcher, mv)
+        });
+
+        BackgroundSearch {
+            handle,
+            stop,
+            finished,
+            move_time,
+        }
+    }
+
+    /// The opponent played the expected move, so the search in progress becomes the real search.
+    /// All work done while pondering is kept, and the search is given the original move time
+    /// before it is stopped, unless it finishes first.
+    fn ponderhit(self) -> (AlphaBeta, EvaledMove) {
+        if let Some(move_time) = self.move_time {
+            let move_time = Duration::from_millis(u64::try_from(move_time).unwrap_or(u64::MAX));
+            self.finished.recv_timeout(move_time).ok();
+        }
+        self.stop()
+    }
+
+    /// Stop the search in progress, returning the searcher and the best move found so far.
+    fn stop(self) -> (AlphaBeta, EvaledMove) {
+        self.stop.store(true, Ordering::Relaxed);
+        let result = self.handle.join().unwrap();
+        self.stop.store(false, Ordering::Relaxed);
+        result
+    }
+}
+
+fn update_position(gen: &MoveGenerator, fen: &String) -> BoardState {
     let v = fen.split_ascii_whitespace().collect_vec();
     let keyword = v.first().unwrap();
     let mut pos = match &keyword[..] {
@@ -51,23 +298,215 @@ fn update_position(fen: &String) -> BoardState {
     let keyword = v.get(1);
 
     if keyword.is_some() {
-        apply_moves(&mut pos, &v[2..]);
+        apply_moves(gen, &mut pos, &v[2..]);
     }
 
     pos
 }
 
-fn apply_moves(pos: &mut BoardState, moves: &[&str]) {
+fn apply_moves(gen: &MoveGenerator, pos: &mut BoardState, moves: &[&str]) {
     for mv_str in moves.iter() {
-        let gen = MoveGenerator::new();
         let move_list = gen.all_moves(pos);
         let mv = move_list.iter().find(|x| x.to_algebraic() == *mv_str);
         pos.make_move(*mv.unwrap());
     }
 }
 
-fn init_uci() {
-    println!("id name Purple");
-    println!("id author Joshua L Ervin");
-    println!("uciok");
+/// Write the given lines to the output, flushing them so that the GUI receives them right away.
+fn reply(out: &mut impl Write, lines: &[&str]) {
+    for line in lines {
+        writeln!(out, "{line}").unwrap();
+    }
+    out.flush().unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chess_move::MoveType;
+    use crate::search::eval::MATE_VALUE;
+
+    #[test]
+    fn handshake_replies_in_order() {
+        let mut out = Vec::new();
+        let script = "uci\nisready\n\nposition startpos moves e2e4 e7e5\nisready\nquit\n";
+        run(script.as_bytes(), &mut out);
+
+        let out = String::from_utf8(out).unwrap();
+        let mut expected = UCI_ID.to_vec();
+        expected.extend(["readyok", "readyok"].iter());
+        assert_eq!(out.lines().collect_vec(), expected);
+    }
+
+    #[test]
+    fn go_nodes_stops_near_node_limit() {
+        let mut pos = BoardState::default();
+        let mut searcher = AlphaBeta::new();
+        let depth = apply_limits(&mut searcher, &pos, &["go", "nodes", "10000"]);
+        assert_eq!(depth, MAX_DEPTH);
+
+        // The limit is checked on entering every node, and in between at most one move completes
+        // at each ply which the search returns through.
+        let mv = searcher.best_move_depth(&mut pos, depth);
+        assert!(searcher.nodes_searched() >= 10000);
+        assert!(searcher.nodes_searched() <= 10000 + depth);
+        assert!(MoveGenerator::new().all_moves(&pos).contains(&mv.mv));
+    }
+
+    #[test]
+    fn go_mate_finds_mate() {
+        let mut pos = parse_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        let mut searcher = AlphaBeta::new();
+        let depth = apply_limits(&mut searcher, &pos, &["go", "mate", "2"]);
+        assert_eq!(depth, 4);
+
+        let mv = searcher.best_move_depth(&mut pos, depth);
+        assert!(["d1d8", "d2d8"].contains(&mv.mv.to_algebraic().as_str()));
+        assert!(mv.eval > MATE_VALUE);
+
+        // Later searches are not mate searches unless asked to be.
+        assert_eq!(apply_limits(&mut searcher, &pos, &["go"]), MAX_DEPTH);
+    }
+
+    #[test]
+    fn go_depth_limits_search_depth() {
+        let pos = BoardState::default();
+        let mut searcher = AlphaBeta::new();
+        assert_eq!(apply_limits(&mut searcher, &pos, &["go", "depth", "3"]), 3);
+    }
+
+    #[test]
+    fn go_clock_budgets_side_to_move() {
+        let data = [
+            "go",
+            "wtime",
+            "60000",
+            "btime",
+            "1000",
+            "winc",
+            "1000",
+            "movestogo",
+            "20",
+        ];
+        let white = BoardState::default();
+        assert_eq!(move_time(&white, &data), Some(3_750));
+        let black = parse_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(move_time(&black, &data), Some(50));
+
+        // An explicit move time takes precedence over the clock.
+        assert_eq!(
+            move_time(&white, &["go", "movetime", "10", "wtime", "60000"]),
+            Some(10)
+        );
+        assert_eq!(move_time(&white, &["go", "depth", "3"]), None);
+    }
+
+    #[test]
+    fn go_ignores_malformed_arguments() {
+        let pos = BoardState::default();
+        let mut searcher = AlphaBeta::new();
+        assert_eq!(
+            apply_limits(&mut searcher, &pos, &["go", "depth", "x"]),
+            MAX_DEPTH
+        );
+        assert_eq!(
+            apply_limits(&mut searcher, &pos, &["go", "depth"]),
+            MAX_DEPTH
+        );
+        assert_eq!(move_time(&pos, &["go", "nodes", "-1", "wtime", "-5"]), None);
+    }
+
+    #[test]
+    fn setoption_toggles_hash_table() {
+        let pos = parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut nodes = Vec::new();
+        for value in ["true", "false", "true"].iter() {
+            let mut searcher = AlphaBeta::new();
+            set_option(
+                &mut Vec::new(),
+                &mut searcher,
+                &["setoption", "name", "UseHashTable", "value", value],
+            );
+            let mv = searcher.best_move_depth(&mut pos.clone(), 4);
+            assert!(MoveGenerator::new().all_moves(&pos).contains(&mv.mv));
+            nodes.push(searcher.nodes_searched());
+        }
+
+        // Without the table nothing is reused between iterations, so more nodes are searched. The
+        // search hashes with seeded keys, so the same search always visits the same nodes.
+        assert!(nodes[0] < nodes[1]);
+        assert_eq!(nodes[0], nodes[2]);
+    }
+
+    #[test]
+    fn setoption_replies_to_bad_options_in_order() {
+        let mut out = Vec::new();
+        let script = "setoption name Foo value 1\nsetoption UseHashTable\nisready\nquit\n";
+        run(script.as_bytes(), &mut out);
+
+        let out = String::from_utf8(out).unwrap();
+        let expected = [
+            "Unknown option Foo with value 1",
+            "Command not understood",
+            "readyok",
+        ];
+        assert_eq!(out.lines().collect_vec(), expected);
+    }
+
+    #[test]
+    fn debug_toggles_diagnostics() {
+        let mut out = Vec::new();
+        let mut searcher = AlphaBeta::new();
+        assert!(!searcher.is_debug());
+        set_debug(&mut out, &mut searcher, &["debug", "on"]);
+        assert!(searcher.is_debug());
+        set_debug(&mut out, &mut searcher, &["debug", "off"]);
+        assert!(!searcher.is_debug());
+        assert!(out.is_empty());
+
+        set_debug(&mut out, &mut searcher, &["debug", "maybe"]);
+        assert_eq!(String::from_utf8(out).unwrap(), "Command not understood\n");
+    }
+
+    #[test]
+    fn ponderhit_reuses_ponder_search() {
+        let pos = parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let ponder = BackgroundSearch::start(AlphaBeta::new(), pos, Some(600_000), 3);
+
+        // The ponder search is only limited by depth, so the ponderhit returns as soon as it is
+        // finished rather than using up the move time.
+        let (searcher, mv) = ponder.ponderhit();
+        assert_eq!(searcher.completed_depth(), 3);
+        assert_ne!(mv.mv.kind, MoveType::Null);
+        assert!(!searcher.stop_flag().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stop_ends_ponder_search() {
+        let pos = parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let ponder = BackgroundSearch::start(AlphaBeta::new(), pos, None, INFINITE_DEPTH);
+
+        // The search could never finish on its own, so it is only the stop which ends it.
+        let (searcher, mv) = ponder.stop();
+        assert!(searcher.completed_depth() < INFINITE_DEPTH);
+        assert!(
+            mv.mv.kind == MoveType::Null || MoveGenerator::new().all_moves(&pos).contains(&mv.mv)
+        );
+        assert!(!searcher.stop_flag().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn go_infinite_searches_until_stopped() {
+        let mut pos =
+            parse_fen("r2qkbnr/ppp2ppp/2np4/8/8/PPPpPbP1/7P/RNBQKBNR w KQkq - 0 8").unwrap();
+        let mut searcher = Some(AlphaBeta::new());
+        let search = go(&mut pos, &mut searcher, &["go", "infinite"]).unwrap();
+        assert!(searcher.is_none());
+
+        let (searcher, mv) = search.stop();
+        assert!(searcher.completed_depth() < INFINITE_DEPTH);
+        assert!(
+            mv.mv.kind == MoveType::Null || MoveGenerator::new().all_moves(&pos).contains(&mv.mv)
+        );
+    }
 }
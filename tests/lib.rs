@@ -1,4 +1,8 @@
-use purple::Game;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use purple::search::{BoardState, Eval, Tablebase, Wdl};
+use purple::{Color, Game, GameStatus, PieceType, TimeControl};
 
 #[test]
 fn should_init_default_game() {
@@ -17,6 +21,326 @@ fn should_init_game_from_fen() {
     assert_eq!(moves.len(), 48);
 }
 
+#[test]
+fn should_reject_malformed_move_counters() {
+    assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 300").is_ok());
+    assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 x").is_err());
+}
+
+#[test]
+fn should_reset_to_startpos() {
+    let mut game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+    for _ in 0..4 {
+        let mv = game.legal_moves()[0];
+        game.make_move(mv).unwrap();
+    }
+    game.reset_to_startpos();
+    assert_eq!(game.legal_moves().len(), 20);
+}
+
+#[test]
+fn should_rank_free_queen_capture_first() {
+    let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+    let moves = game.legal_moves_evaluated();
+    assert_eq!(moves.len(), game.legal_moves().len());
+    assert_eq!(moves[0].mv.to_algebraic(), "d2d5");
+    assert!(moves.windows(2).all(|w| w[0].eval >= w[1].eval));
+}
+
+#[test]
+fn should_detect_checkmate_and_stalemate() {
+    let start = Game::new();
+    assert!(!start.in_checkmate());
+    assert!(!start.in_stalemate());
+
+    let checkmate =
+        Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert!(checkmate.in_checkmate());
+    assert!(!checkmate.in_stalemate());
+
+    let stalemate = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(stalemate.in_stalemate());
+    assert!(!stalemate.in_checkmate());
+}
+
+#[test]
+fn should_map_attacked_squares() {
+    let game = Game::new();
+    let rank = |n: u32| 0xffu64 << (8 * (n - 1));
+    // Every square on the second and third ranks, and all of the first rank except the corners.
+    let white = rank(3) | rank(2) | (rank(1) & !0x81);
+    assert_eq!(game.attack_map(Color::White), white);
+    assert_eq!(game.attack_map(Color::White).count_ones(), 22);
+    assert_eq!(game.attack_map(Color::Black), white.swap_bytes());
+
+    // The rook's attacks run through the black king to the edge of the board.
+    let game = Game::from_fen("8/8/8/8/R3k3/8/8/7K b - - 0 1").unwrap();
+    assert_ne!(game.attack_map(Color::White) & (1 << 31), 0);
+}
+
+#[test]
+fn should_list_legal_destinations() {
+    let game = Game::new();
+    // The knight on b1 can reach a3 and c3.
+    assert_eq!(game.legal_destinations(1), (1 << 16) | (1 << 18));
+    assert_eq!(game.legal_moves_from(1).len(), 2);
+    // The rook on a1 is boxed in by its own pieces.
+    assert_eq!(game.legal_destinations(0), 0);
+    // Empty squares and opposing pieces have no destinations.
+    assert_eq!(game.legal_destinations(27), 0);
+    assert_eq!(game.legal_destinations(57), 0);
+}
+
+#[test]
+fn should_check_single_moves_for_legality() {
+    let game = Game::new();
+    let e4 = game.move_from_san("e4").unwrap();
+    assert!(game.is_legal(e4));
+
+    // The rook on f7 covers f1, so the king may not step there.
+    let kf1 = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+        .unwrap()
+        .move_from_san("Kf1")
+        .unwrap();
+    let game = Game::from_fen("4k3/5r2/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert!(!game.is_legal(kf1));
+    assert!(game.is_legal(game.move_from_san("Kd1").unwrap()));
+
+    // Moves which the piece on their origin square cannot make are illegal.
+    let game = Game::new();
+    assert!(!game.is_legal(kf1));
+    let bb5 = Game::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 0 1")
+        .unwrap()
+        .move_from_san("Bb5")
+        .unwrap();
+    assert!(!game.is_legal(bb5));
+
+    // Without a king, no move is legal.
+    let ra2 = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")
+        .unwrap()
+        .move_from_san("Ra2")
+        .unwrap();
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R7 w - - 0 1").unwrap();
+    assert!(!game.is_legal(ra2));
+}
+
+#[test]
+fn should_tell_claimable_draws_from_automatic_draws() {
+    let mut game = Game::new();
+    let shuffle = |game: &mut Game| {
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8"].iter() {
+            let mv = game.move_from_san(san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+    };
+    assert!(!game.can_claim_draw());
+
+    shuffle(&mut game);
+    let hashes = game.zobrist_history();
+    assert_eq!(hashes.len(), 5);
+    assert_eq!(hashes[0], hashes[4]);
+    assert!(game.can_claim_draw());
+    assert!(!game.is_draw());
+
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    assert!(game.to_pgn(&[]).ends_with(" *\n"));
+
+    shuffle(&mut game);
+    assert!(game.can_claim_draw());
+    assert!(game.is_draw());
+    assert_eq!(game.status(), GameStatus::DrawByRepetition);
+    assert!(game.to_pgn(&[]).ends_with(" 1/2-1/2\n"));
+
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+    assert!(game.can_claim_draw());
+    assert!(!game.is_draw());
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 100").unwrap();
+    assert!(game.is_draw());
+}
+
+#[test]
+fn should_count_attackers_of_king() {
+    let attackers = |fen: &str| Game::from_fen(fen).unwrap().king_attackers(Color::White);
+    assert_eq!(attackers("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1"), 0);
+    // The queen eyes f2 and h2, then the knight joins in, and then the bishop aims at g2.
+    assert_eq!(attackers("6k1/8/8/8/7q/8/5PPP/6K1 w - - 0 1"), 1);
+    assert_eq!(attackers("6k1/8/8/8/6nq/8/5PPP/6K1 w - - 0 1"), 2);
+    assert_eq!(attackers("6k1/8/8/3b4/6nq/8/5PPP/6K1 w - - 0 1"), 3);
+
+    let game = Game::from_fen("6k1/8/8/3b4/6nq/8/5PPP/6K1 w - - 0 1").unwrap();
+    assert_eq!(game.king_attackers(Color::Black), 0);
+}
+
+#[test]
+fn should_apply_fifty_move_rule_through_clock_not_hash() {
+    let fresh = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    let stale = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 100").unwrap();
+    assert_eq!(fresh.zobrist_history(), stale.zobrist_history());
+    assert_eq!(fresh.status(), GameStatus::Ongoing);
+    assert_eq!(stale.status(), GameStatus::DrawBySeventyFiveMove);
+}
+
+#[test]
+fn should_report_search_results() {
+    let mut game = Game::new();
+    let result = game.search(3).unwrap();
+    assert_eq!(result.depth, 3);
+    assert_eq!(result.pv.first(), Some(&result.best_move));
+    assert!(game.legal_moves().contains(&result.best_move));
+
+    let mut game = Game::from_fen("k7/8/1QK5/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(game.search(3).is_none());
+}
+
+#[test]
+fn should_find_pins_and_checks() {
+    // The knight on d2 is pinned by the bishop on b4, and the rook on e8 gives check.
+    let game = Game::from_fen("k3r3/8/8/8/1b6/8/3N4/4K3 w - - 0 1").unwrap();
+    assert_eq!(game.pinned_pieces(), 1 << 11);
+    assert_eq!(game.checkers(), 1 << 60);
+
+    let game = Game::new();
+    assert_eq!(game.pinned_pieces(), 0);
+    assert_eq!(game.checkers(), 0);
+}
+
+#[test]
+fn should_set_up_positions_from_pieces() {
+    let pieces = [
+        (4, Color::White, PieceType::King),
+        (3, Color::White, PieceType::Queen),
+        (60, Color::Black, PieceType::King),
+    ];
+    let game = Game::from_pieces(&pieces, Color::Black).unwrap();
+    assert_eq!(game.fen(), "4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+    // The black king may not step onto the d file or next to the white king.
+    assert_eq!(game.legal_moves().len(), 3);
+
+    assert!(Game::from_pieces(&pieces[..2], Color::White).is_err());
+    let pawn = [
+        (4, Color::White, PieceType::King),
+        (60, Color::Black, PieceType::King),
+        (0, Color::White, PieceType::Pawn),
+    ];
+    assert!(Game::from_pieces(&pawn, Color::White).is_err());
+}
+
+#[test]
+fn should_render_single_moves_in_san() {
+    let find = |game: &Game, algebraic: &str| {
+        game.legal_moves()
+            .into_iter()
+            .find(|mv| mv.to_algebraic() == algebraic)
+            .unwrap()
+    };
+
+    let game = Game::new();
+    assert_eq!(game.san(find(&game, "g1f3")), "Nf3");
+
+    let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    assert_eq!(game.san(find(&game, "e1g1")), "O-O");
+    assert_eq!(game.san(find(&game, "e1c1")), "O-O-O");
+    assert_eq!(game.san(find(&game, "a1a8")), "Rxa8+");
+
+    // Both rooks can reach d1.
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1").unwrap();
+    assert_eq!(game.san(find(&game, "a1d1")), "Rad1");
+}
+
+#[test]
+fn should_give_a_hint_without_changing_the_game() {
+    let mut game = Game::new();
+    game.best_move_depth(3);
+    let nodes = game.stats().nodes;
+
+    let hint = game.hint_move(4).unwrap();
+    assert!(game.legal_moves().contains(&hint));
+    assert_eq!(game.stats().nodes, nodes);
+    assert_eq!(game.fen(), Game::new().fen());
+}
+
+/// Counts how often the search consults it, both as an evaluation and as a tablebase.
+struct Counting(Arc<AtomicUsize>);
+
+impl Eval for Counting {
+    fn evaluate(&self, _pos: &BoardState) -> isize {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        0
+    }
+}
+
+impl Tablebase for Counting {
+    fn max_pieces(&self) -> u32 {
+        4
+    }
+
+    fn probe_wdl(&self, _pos: &BoardState) -> Option<Wdl> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+#[test]
+fn should_give_hints_with_the_games_eval_and_tablebase() {
+    let mut game = Game::from_fen("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+    let evals = Arc::new(AtomicUsize::new(0));
+    let probes = Arc::new(AtomicUsize::new(0));
+    game.set_eval(Box::new(Counting(Arc::clone(&evals))));
+    game.set_tablebase(Box::new(Counting(Arc::clone(&probes))));
+
+    assert!(game.hint_move(3).is_some());
+    assert!(evals.load(Ordering::Relaxed) > 0);
+    assert!(probes.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn should_not_find_a_move_when_the_game_is_over() {
+    let mut checkmate =
+        Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert!(checkmate.best_move().is_none());
+    assert!(checkmate.best_move_depth(3).is_none());
+
+    let mut stalemate = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(stalemate.best_move().is_none());
+}
+
+#[test]
+fn should_prefer_checkmate_over_seventy_five_move_draw() {
+    let fen = "6k1/5ppp/8/8/8/8/5PPP/4R1K1 w - - 149 100";
+    let mut game = Game::from_fen(fen).unwrap();
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    let mate = *game
+        .legal_moves()
+        .iter()
+        .find(|mv| mv.to_algebraic() == "e1e8")
+        .unwrap();
+    game.make_move(mate).unwrap();
+    assert_eq!(game.status(), GameStatus::Checkmate);
+
+    let mut game = Game::from_fen(fen).unwrap();
+    let quiet = *game
+        .legal_moves()
+        .iter()
+        .find(|mv| mv.to_algebraic() == "e1e2")
+        .unwrap();
+    game.make_move(quiet).unwrap();
+    assert_eq!(game.status(), GameStatus::DrawBySeventyFiveMove);
+}
+
+#[test]
+fn should_evaluate_from_whites_perspective() {
+    let white = Game::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").unwrap();
+    let black = Game::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 b - - 0 1").unwrap();
+    assert_eq!(white.evaluation_white(), 0);
+    assert_eq!(black.evaluation_white(), 0);
+
+    let white = Game::from_fen("4k3/ppp5/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").unwrap();
+    let black = Game::from_fen("4k3/ppp5/8/8/8/8/PPPPPPPP/4K3 b - - 0 1").unwrap();
+    assert!(white.evaluation_white() > 0);
+    assert_eq!(white.evaluation_white(), black.evaluation_white());
+}
+
 #[test]
 fn should_find_mate_in_one() {
     let mut game = Game::new();
@@ -25,3 +349,171 @@ fn should_find_mate_in_one() {
 
 #[test]
 fn should_correctly_run_perft_test() {}
+
+#[test]
+fn should_resolve_epd_best_move() {
+    let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+    let (game, operations) = Game::from_epd(epd).unwrap();
+    assert_eq!(operations["id"], "WAC.001");
+    let bm = game.move_from_san(&operations["bm"]).unwrap();
+    assert_eq!(bm.to_algebraic(), "g3g6");
+
+    let game = Game::new();
+    assert_eq!(game.move_from_san("Nf3").unwrap().to_algebraic(), "g1f3");
+    assert_eq!(game.move_from_san("e4").unwrap().to_algebraic(), "e2e4");
+    assert!(game.move_from_san("Nd2").is_none());
+
+    let game = Game::from_fen("7K/P7/8/8/8/k7/8/R6R w - - 0 1").unwrap();
+    assert_eq!(game.move_from_san("a8=Q+").unwrap().to_algebraic(), "a7a8q");
+    assert_eq!(game.move_from_san("Rad1").unwrap().to_algebraic(), "a1d1");
+    assert!(game.move_from_san("Rd1").is_none());
+}
+
+#[test]
+fn should_evaluate_static_exchanges() {
+    let game = Game::from_fen("4k3/8/2p5/3n4/8/8/8/3RK3 w - - 0 1").unwrap();
+    assert_eq!(game.see(game.move_from_san("Rxd5").unwrap()), -200);
+
+    let game = Game::from_fen("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1").unwrap();
+    assert_eq!(game.see(game.move_from_san("Rxd5").unwrap()), 300);
+
+    // The rook on d1 only attacks d5 once the rook in front of it has captured.
+    let game = Game::from_fen("4k3/8/2p5/3n4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+    assert_eq!(game.see(game.move_from_san("Rxd5").unwrap()), -100);
+
+    // The king cannot recapture on a square which is still defended.
+    let game = Game::from_fen("8/8/8/3k4/4p3/8/8/K3R2B w - - 0 1").unwrap();
+    assert_eq!(game.see(game.move_from_san("Bxe4+").unwrap()), 100);
+}
+
+#[test]
+fn should_track_en_passant_square() {
+    let mut game = Game::new();
+    game.make_move(game.move_from_san("e4").unwrap()).unwrap();
+    assert_eq!(game.en_passant_square(), Some("e3".to_string()));
+    assert_eq!(
+        game.fen(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    );
+    assert_eq!(
+        game.fen_legal_en_passant(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+    );
+
+    game.make_move(game.move_from_san("Nf6").unwrap()).unwrap();
+    assert_eq!(game.en_passant_square(), None);
+    assert!(game.fen().contains(" w KQkq - "));
+
+    let mut game = Game::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+    game.make_move(game.move_from_san("d5").unwrap()).unwrap();
+    assert_eq!(
+        game.fen_legal_en_passant(),
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2"
+    );
+}
+
+#[test]
+fn should_manage_time_control() {
+    let mut game = Game::new();
+    game.set_time_control(TimeControl {
+        base: 3_000,
+        increment: 0,
+        moves_to_go: None,
+    });
+
+    // The budgets themselves are tested deterministically alongside `Clock`, so this only checks
+    // that the game keeps the clock and that no search comes close to running it out.
+    let mut remaining = game.time_remaining().unwrap();
+    for _ in 0..4 {
+        let start = std::time::Instant::now();
+        let mv = game.best_move().unwrap();
+        let elapsed = start.elapsed().as_millis();
+        game.make_move(mv.mv).unwrap();
+        assert!(elapsed < remaining, "{} {}", elapsed, remaining);
+
+        let now = game.time_remaining().unwrap();
+        assert!(now > 0 && now <= remaining);
+        remaining = now;
+    }
+}
+
+#[test]
+fn should_tell_whether_a_move_gives_check() {
+    let mut game = Game::new();
+    game.make_moves(&["e2e4", "f7f6"]).unwrap();
+    let queen = game.move_from_san("Qh5").unwrap();
+    assert!(game.in_check_after(queen));
+    let pawn = game.move_from_san("a3").unwrap();
+    assert!(!game.in_check_after(pawn));
+
+    // Neither move was played.
+    assert_eq!(game.history().len(), 2);
+    assert_eq!(game.checkers(), 0);
+}
+
+#[test]
+fn should_apply_batches_of_moves() {
+    let mut game = Game::new();
+    game.make_moves(&["e2e4", "e7e5", "g1f3", "b8c6", "f1c4"])
+        .unwrap();
+    assert_eq!(
+        game.fen(),
+        "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+    );
+    assert_eq!(game.history().len(), 5);
+
+    let mut game = Game::new();
+    let err = game
+        .make_moves(&["e2e4", "e7e5", "e4e5", "g8f6"])
+        .unwrap_err();
+    assert!(err.contains('2'));
+    assert_eq!(game.history().len(), 2);
+}
+
+#[test]
+fn should_record_move_history() {
+    let mut game = Game::new();
+    let mut played = Vec::new();
+    for san in ["e4", "e5", "Nf3"].iter() {
+        let mv = game.move_from_san(san).unwrap();
+        game.make_move(mv).unwrap();
+        played.push(mv);
+    }
+    assert_eq!(game.history().len(), 3);
+    assert_eq!(game.history(), &played[..]);
+
+    game.reset_to_startpos();
+    assert!(game.history().is_empty());
+}
+
+#[test]
+fn should_export_pgn_which_replays() {
+    let mut game = Game::new();
+    for san in ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"].iter() {
+        let mv = game.move_from_san(san).unwrap();
+        game.make_move(mv).unwrap();
+    }
+    let pgn = game.to_pgn(&[]);
+    assert!(pgn.starts_with("[Event \"?\"]\n"));
+    assert!(pgn.contains("[Result \"1-0\"]\n"));
+    assert!(pgn.ends_with("\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"));
+
+    let movetext = pgn.lines().last().unwrap();
+    let mut replay = Game::new();
+    for token in movetext.split_whitespace() {
+        if token.ends_with('.') || token == "1-0" {
+            continue;
+        }
+        let mv = replay.move_from_san(token).unwrap();
+        replay.make_move(mv).unwrap();
+    }
+    assert_eq!(replay.history(), game.history());
+    assert!(replay.in_checkmate());
+
+    let tags = [("White".to_string(), "Purple".to_string())];
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 40").unwrap();
+    let pgn = game.to_pgn(&tags);
+    assert!(pgn.starts_with("[White \"Purple\"]\n"));
+    assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 40\"]\n"));
+    assert!(pgn.ends_with("\n*\n"));
+}